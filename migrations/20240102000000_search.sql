@@ -0,0 +1,7 @@
+CREATE VIRTUAL TABLE IF NOT EXISTS content_search USING fts5(
+    alias UNINDEXED,
+    item_id UNINDEXED,
+    title,
+    body,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
@@ -0,0 +1 @@
+ALTER TABLE collections ADD COLUMN revision INTEGER NOT NULL DEFAULT 1;
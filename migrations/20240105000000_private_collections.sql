@@ -0,0 +1 @@
+ALTER TABLE collections ADD COLUMN private INTEGER NOT NULL DEFAULT 0;
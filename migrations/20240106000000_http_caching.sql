@@ -0,0 +1,3 @@
+ALTER TABLE collections ADD COLUMN content_hash TEXT;
+ALTER TABLE collections ADD COLUMN updated_at TEXT;
+ALTER TABLE collections ADD COLUMN cache_control TEXT;
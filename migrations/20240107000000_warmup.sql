@@ -0,0 +1,7 @@
+ALTER TABLE collections ADD COLUMN preload INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS collection_access (
+    alias TEXT PRIMARY KEY,
+    hits INTEGER NOT NULL DEFAULT 0,
+    last_accessed_at TEXT NOT NULL
+);
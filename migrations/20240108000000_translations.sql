@@ -0,0 +1,4 @@
+ALTER TABLE collections ADD COLUMN translation_group TEXT;
+ALTER TABLE collections ADD COLUMN aligned_by_position INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_collections_translation_group ON collections(translation_group);
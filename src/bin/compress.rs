@@ -1,23 +1,8 @@
-use lz4_flex::frame::FrameEncoder;
-use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
-use std::io::Write;
+use tulpar_api::modules::content::chunked;
+use tulpar_api::modules::content::store::{validate_collection, ContentCollection, ContentItem};
+use std::fs;
 use std::path::Path;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ContentItem {
-    id: String,
-    title: String,
-    body: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ContentCollection {
-    id: String,
-    name: String,
-    items: Vec<ContentItem>,
-}
-
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = std::env::args().collect();
 
@@ -29,7 +14,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         None => {
             eprintln!("Usage:");
-            eprintln!("  cargo run --bin compress -- <input.json> [output.json.lz4]");
+            eprintln!("  cargo run --bin compress -- <input.json> [output.tlpc]");
             eprintln!("  cargo run --bin compress -- --demo");
             std::process::exit(1);
         }
@@ -46,15 +31,12 @@ fn compress_file(input_path: &str, output_path: Option<&str>) -> Result<(), Box<
 
     let output = match output_path {
         Some(p) => p.to_string(),
-        None => format!("{}.lz4", input_path),
+        None => input.with_extension(chunked::EXTENSION).to_string_lossy().into_owned(),
     };
 
     let json_data = fs::read(input)?;
-
-    let output_file = File::create(&output)?;
-    let mut encoder = FrameEncoder::new(output_file);
-    encoder.write_all(&json_data)?;
-    encoder.finish()?;
+    let collection = parse_collection(&json_data)?;
+    fs::write(&output, chunked::encode(&collection)?.bytes)?;
 
     let original_size = json_data.len();
     let compressed_size = fs::metadata(&output)?.len() as usize;
@@ -64,10 +46,18 @@ fn compress_file(input_path: &str, output_path: Option<&str>) -> Result<(), Box<
     println!("  Original:   {} bytes", original_size);
     println!("  Compressed: {} bytes", compressed_size);
     println!("  Ratio:      {:.1}%", ratio);
+    println!("  Items:      {}", collection.items.len());
 
     Ok(())
 }
 
+/// Parses and validates a collection before it is written to storage.
+fn parse_collection(json: &[u8]) -> Result<ContentCollection, Box<dyn std::error::Error>> {
+    let collection: ContentCollection = serde_json::from_slice(json)?;
+    validate_collection(&collection).map_err(|e| format!("Invalid collection: {}", e))?;
+    Ok(collection)
+}
+
 fn create_demo_data() -> Result<(), Box<dyn std::error::Error>> {
     fs::create_dir_all("data")?;
     fs::create_dir_all("storage/collections")?;
@@ -80,11 +70,13 @@ fn create_demo_data() -> Result<(), Box<dyn std::error::Error>> {
                 id: "1".to_string(),
                 title: "Quatrain I".to_string(),
                 body: "Awake! for Morning in the Bowl of Night...".to_string(),
+                ..Default::default()
             },
             ContentItem {
                 id: "2".to_string(),
                 title: "Quatrain II".to_string(),
                 body: "Dreaming when Dawn's Left Hand was in the Sky...".to_string(),
+                ..Default::default()
             },
         ],
     };
@@ -98,12 +90,11 @@ fn create_demo_data() -> Result<(), Box<dyn std::error::Error>> {
          println!("Skipped creation of {} (already exists)", json_path);
     }
 
+    // The demo row registered by the initial migration points at this path;
+    // the server recognises the chunked format by its header, not its name.
     let lz4_path = "storage/collections/rubaiyat.json.lz4";
-    let output_file = File::create(lz4_path)?;
-    let mut encoder = FrameEncoder::new(output_file);
-    let full_json_data = fs::read(json_path)?;
-    encoder.write_all(&full_json_data)?;
-    encoder.finish()?;
+    let collection = parse_collection(&fs::read(json_path)?)?;
+    fs::write(lz4_path, chunked::encode(&collection)?.bytes)?;
     println!("Created: {}", lz4_path);
 
     println!("\nDemo data ready. Start server and test:");
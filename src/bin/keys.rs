@@ -0,0 +1,54 @@
+use tulpar_api::modules::auth::principal::parse_scopes;
+use tulpar_api::modules::auth::ApiKeyStore;
+use tulpar_api::{config::Config, db};
+use std::env;
+
+fn usage() -> ! {
+    eprintln!("Usage:");
+    eprintln!("  cargo run --bin keys -- create <name> <scopes>   (scopes: read,write,admin)");
+    eprintln!("  cargo run --bin keys -- revoke <id>");
+    eprintln!("  cargo run --bin keys -- list");
+    std::process::exit(1);
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().collect();
+
+    let config = Config::from_env();
+    let pool = db::establish_connection(&config.database_url).await?;
+    db::run_migrations(&pool).await?;
+    let keys = ApiKeyStore::new(pool);
+
+    match (args.get(1).map(|s| s.as_str()), args.get(2), args.get(3)) {
+        (Some("create"), Some(name), Some(scopes)) => {
+            let scopes = parse_scopes(scopes)?;
+            let (record, key) = keys.create(name, &scopes).await?;
+            println!("Created API key {} ({}) with scopes: {}", record.id, record.name, record.scopes);
+            println!();
+            println!("  {}", key);
+            println!();
+            println!("Store it now; it cannot be shown again.");
+        }
+        (Some("revoke"), Some(id), None) => {
+            let id: i64 = id.parse().map_err(|_| "Key id must be a number")?;
+            keys.revoke(id).await?;
+            println!("Revoked API key {}", id);
+        }
+        (Some("list"), None, None) => {
+            for key in keys.list().await? {
+                let status = match &key.revoked_at {
+                    Some(at) => format!("revoked {}", at),
+                    None => "active".to_string(),
+                };
+                println!(
+                    "{:>4}  {:<20}  {}…  {:<16}  created {}  {}",
+                    key.id, key.name, key.key_prefix, key.scopes, key.created_at, status
+                );
+            }
+        }
+        _ => usage(),
+    }
+
+    Ok(())
+}
@@ -1,8 +1,9 @@
+use tulpar_api::modules::content::store::{validate_collection, CollectionUpsert, ContentCollection, WriteMode};
+use tulpar_api::modules::content::ContentStore;
+use tulpar_api::modules::metrics::Metrics;
 use tulpar_api::{config::Config, db};
-use lz4_flex::frame::FrameEncoder;
 use std::env;
-use std::fs::{self, File};
-use std::io::Write;
+use std::fs;
 use std::path::Path;
 
 #[tokio::main]
@@ -10,7 +11,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
 
     if args.len() < 4 {
-        eprintln!("Usage: cargo run --bin seed -- <input_json> <alias> <name> [language]");
+        eprintln!("Usage: cargo run --bin seed -- <input_json> <alias> <name> [language] [translation_group]");
         std::process::exit(1);
     }
 
@@ -18,6 +19,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let alias = &args[2];
     let name = &args[3];
     let language = args.get(4).cloned();
+    let translation_group = args.get(5).cloned();
 
     println!("Seeding collection:");
     println!("  Input: {}", input_path);
@@ -26,40 +28,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     if let Some(lang) = &language {
         println!("  Lang:  {}", lang);
     }
+    if let Some(group) = &translation_group {
+        println!("  Group: {}", group);
+    }
 
     let config = Config::from_env();
     let pool = db::establish_connection(&config.database_url).await?;
+    db::run_migrations(&pool).await?;
 
     let input = Path::new(input_path);
     if !input.exists() {
         return Err(format!("Input file not found: {}", input_path).into());
     }
 
-    let storage_dir = config.storage_path.join("collections");
-    fs::create_dir_all(&storage_dir)?;
-
-    let filename = format!("{}.json.lz4", alias);
-    let output_path = storage_dir.join(&filename);
-
     let json_data = fs::read(input)?;
-    let output_file = File::create(&output_path)?;
-    let mut encoder = FrameEncoder::new(output_file);
-    encoder.write_all(&json_data)?;
-    encoder.finish()?;
-
-    println!("Compressed to: {:?}", output_path);
+    let collection: ContentCollection = serde_json::from_slice(&json_data)?;
+    validate_collection(&collection).map_err(|e| format!("Invalid collection: {}", e))?;
+    let item_count = collection.items.len();
 
-    let file_path_db = format!("storage/collections/{}", filename);
-    
-    sqlx::query("INSERT INTO collections (alias, name, file_path, language) VALUES (?, ?, ?, ?) ON CONFLICT(alias) DO UPDATE SET name=excluded.name, file_path=excluded.file_path, language=excluded.language")
-        .bind(alias)
-        .bind(name)
-        .bind(file_path_db)
-        .bind(language)
-        .execute(&pool)
-        .await?;
+    let store = ContentStore::new(&config, pool, Metrics::default());
+    let existing = store.get_metadata(alias).await.ok();
+    let upsert = CollectionUpsert {
+        name: name.clone(),
+        language,
+        private: existing.as_ref().map(|meta| meta.private).unwrap_or(false),
+        preload: existing.as_ref().map(|meta| meta.preload).unwrap_or(false),
+        translation_group: translation_group.or_else(|| existing.as_ref().and_then(|meta| meta.translation_group.clone())),
+        aligned_by_position: existing.as_ref().map(|meta| meta.aligned_by_position).unwrap_or(false),
+        cache_control: existing.and_then(|meta| meta.cache_control),
+        content: collection,
+    };
+    let meta = store.save_collection(alias, upsert, WriteMode::Upsert).await?;
 
+    println!("Compressed to: {:?}", config.storage_path.join(&meta.file_path));
     println!("Database updated successfully.");
+    println!("Indexed {} items for search.", item_count);
 
     Ok(())
 }
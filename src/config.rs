@@ -1,6 +1,38 @@
+use crate::middleware::RouteGroup;
+use crate::modules::content::encoding::Encoding;
+use crate::modules::content::warmup::WarmupMode;
 use dotenvy::dotenv;
+use ipnet::IpNet;
 use std::env;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Sustained requests per second and burst size, written as `2:5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub per_second: u32,
+    pub burst: u32,
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (per_second, burst) = s
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| format!("Rate limit must look like <per_second>:<burst>, got {}", s))?;
+        let parse = |v: &str| match v.trim().parse::<u32>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("Rate limit values must be positive integers, got {}", s)),
+        };
+        Ok(Self {
+            per_second: parse(per_second)?,
+            burst: parse(burst)?,
+        })
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -8,17 +40,182 @@ pub struct Config {
     pub storage_path: PathBuf,
     pub host: String,
     pub port: u16,
+    /// Whether unauthenticated callers get the `read` scope.
+    pub public_read: bool,
+    /// Origins allowed to make cross-origin requests. When empty, any origin
+    /// may read but browsers cannot issue cross-origin writes.
+    pub cors_allowed_origins: Vec<String>,
+    /// Shared secret for HS256 bearer tokens.
+    pub jwt_secret: Option<String>,
+    /// JWKS file with the public keys for RS256 bearer tokens.
+    pub jwt_jwks_path: Option<PathBuf>,
+    pub jwt_issuer: Option<String>,
+    pub jwt_audience: Option<String>,
+    /// `Cache-Control` for content responses of collections without their own.
+    pub default_cache_control: String,
+    /// Content codings used for response compression and pre-encoded
+    /// collections. Empty disables compression.
+    pub compression: Vec<Encoding>,
+    /// Responses smaller than this many bytes are sent uncompressed.
+    pub compression_min_size: u16,
+    /// Collections whose JSON is at least this many bytes are compressed as
+    /// they stream, rather than encoded once and cached, which would hold
+    /// the whole payload in memory.
+    pub stream_encoding_min_size: u64,
+    /// Limit per client IP for anonymous requests; `None` disables it.
+    pub rate_limit_ip: Option<RateLimit>,
+    /// Limit per API key or token subject for authenticated requests.
+    pub rate_limit_key: Option<RateLimit>,
+    /// Additional limits per route group, applied per client.
+    pub rate_limit_groups: Vec<(RouteGroup, RateLimit)>,
+    /// Proxies whose `X-Forwarded-For` header is trusted to carry the client IP.
+    pub trusted_proxies: Vec<IpNet>,
+    /// Upper bound for each readiness check.
+    pub health_check_timeout: Duration,
+    /// How long readiness fails before the listener closes on shutdown, so
+    /// load balancers see the failing probe and stop routing new traffic
+    /// first. Should cover a few probe intervals.
+    pub shutdown_readiness_delay: Duration,
+    /// How long in-flight requests may take to finish after the listener
+    /// closes.
+    pub shutdown_drain_timeout: Duration,
+    /// Reload collections whose files under `storage_path` change.
+    pub watch_storage: bool,
+    /// How often the `collections` table is checked for rows changed by
+    /// other processes; `None` disables polling.
+    pub collections_poll_interval: Option<Duration>,
+    /// Budget for cached collections, weighed by decompressed JSON size.
+    pub cache_max_bytes: u64,
+    /// Cached collections are reloaded after this long; `None` keeps them
+    /// until evicted.
+    pub cache_ttl: Option<Duration>,
+    /// Cached collections unused for this long are dropped.
+    pub cache_tti: Option<Duration>,
+    /// Collections loaded into the cache before readiness turns green.
+    pub warmup: WarmupMode,
+    /// Collections loaded concurrently during warm-up.
+    pub warmup_concurrency: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            database_url: "sqlite:data.db?mode=rwc".into(),
+            storage_path: "storage".into(),
+            host: "0.0.0.0".into(),
+            port: 3000,
+            public_read: true,
+            cors_allowed_origins: Vec::new(),
+            jwt_secret: None,
+            jwt_jwks_path: None,
+            jwt_issuer: None,
+            jwt_audience: None,
+            default_cache_control: "public, max-age=60".into(),
+            compression: vec![Encoding::Brotli, Encoding::Zstd, Encoding::Gzip, Encoding::Deflate],
+            compression_min_size: 1024,
+            stream_encoding_min_size: 8 * 1024 * 1024,
+            rate_limit_ip: Some(RateLimit { per_second: 2, burst: 5 }),
+            rate_limit_key: Some(RateLimit { per_second: 20, burst: 40 }),
+            rate_limit_groups: Vec::new(),
+            trusted_proxies: Vec::new(),
+            health_check_timeout: Duration::from_millis(1000),
+            shutdown_readiness_delay: Duration::from_secs(5),
+            shutdown_drain_timeout: Duration::from_secs(30),
+            watch_storage: true,
+            collections_poll_interval: Some(Duration::from_secs(10)),
+            cache_max_bytes: 256 * 1024 * 1024,
+            cache_ttl: Some(Duration::from_secs(3600)),
+            cache_tti: None,
+            warmup: WarmupMode::Preload,
+            warmup_concurrency: 4,
+        }
+    }
 }
 
 impl Config {
     pub fn from_env() -> Self {
         dotenv().ok();
+        let defaults = Self::default();
 
         Self {
-            database_url: env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:data.db?mode=rwc".into()),
-            storage_path: env::var("STORAGE_PATH").unwrap_or_else(|_| "storage".into()).into(),
-            host: env::var("HOST").unwrap_or_else(|_| "0.0.0.0".into()),
-            port: env::var("PORT").unwrap_or_else(|_| "3000".into()).parse().expect("PORT must be a number"),
+            database_url: env::var("DATABASE_URL").unwrap_or(defaults.database_url),
+            storage_path: env::var("STORAGE_PATH").map(PathBuf::from).unwrap_or(defaults.storage_path),
+            host: env::var("HOST").unwrap_or(defaults.host),
+            port: env::var("PORT").map(|v| v.parse().expect("PORT must be a number")).unwrap_or(defaults.port),
+            public_read: env::var("PUBLIC_READ").map(|v| v != "false" && v != "0").unwrap_or(defaults.public_read),
+            cors_allowed_origins: env::var("CORS_ALLOWED_ORIGINS")
+                .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                .unwrap_or(defaults.cors_allowed_origins),
+            jwt_secret: non_empty_var("JWT_SECRET"),
+            jwt_jwks_path: non_empty_var("JWT_JWKS_PATH").map(PathBuf::from),
+            jwt_issuer: non_empty_var("JWT_ISSUER"),
+            jwt_audience: non_empty_var("JWT_AUDIENCE"),
+            default_cache_control: non_empty_var("DEFAULT_CACHE_CONTROL").unwrap_or(defaults.default_cache_control),
+            compression: env::var("COMPRESSION_ALGORITHMS")
+                .map(|v| {
+                    v.split(',')
+                        .filter(|s| !s.trim().is_empty())
+                        .map(|s| match Encoding::parse(s) {
+                            Some(Encoding::Identity | Encoding::Lz4) | None => {
+                                panic!("Unsupported COMPRESSION_ALGORITHMS entry: {}", s)
+                            }
+                            Some(encoding) => encoding,
+                        })
+                        .collect()
+                })
+                .unwrap_or(defaults.compression),
+            compression_min_size: env::var("COMPRESSION_MIN_SIZE")
+                .map(|v| v.parse().expect("COMPRESSION_MIN_SIZE must be a number up to 65535"))
+                .unwrap_or(defaults.compression_min_size),
+            stream_encoding_min_size: env::var("STREAM_ENCODING_MIN_SIZE")
+                .map(|v| v.parse().expect("STREAM_ENCODING_MIN_SIZE must be a number"))
+                .unwrap_or(defaults.stream_encoding_min_size),
+            rate_limit_ip: rate_limit_var("RATE_LIMIT_IP").unwrap_or(defaults.rate_limit_ip),
+            rate_limit_key: rate_limit_var("RATE_LIMIT_API_KEY").unwrap_or(defaults.rate_limit_key),
+            rate_limit_groups: env::var("RATE_LIMIT_GROUPS")
+                .map(|v| {
+                    v.split(',')
+                        .filter(|s| !s.trim().is_empty())
+                        .map(|entry| {
+                            let (group, limit) = entry.split_once('=').expect("RATE_LIMIT_GROUPS entries must look like <group>=<per_second>:<burst>");
+                            let group = group.parse().unwrap_or_else(|e| panic!("RATE_LIMIT_GROUPS: {}", e));
+                            let limit = limit.parse().unwrap_or_else(|e| panic!("RATE_LIMIT_GROUPS: {}", e));
+                            (group, limit)
+                        })
+                        .collect()
+                })
+                .unwrap_or(defaults.rate_limit_groups),
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .map(|v| {
+                    v.split(',')
+                        .filter(|s| !s.trim().is_empty())
+                        .map(|s| parse_net(s.trim()).unwrap_or_else(|| panic!("Invalid TRUSTED_PROXIES entry: {}", s)))
+                        .collect()
+                })
+                .unwrap_or(defaults.trusted_proxies),
+            health_check_timeout: env::var("HEALTH_CHECK_TIMEOUT_MS")
+                .map(|v| Duration::from_millis(v.parse().expect("HEALTH_CHECK_TIMEOUT_MS must be a number")))
+                .unwrap_or(defaults.health_check_timeout),
+            shutdown_readiness_delay: env::var("SHUTDOWN_READINESS_DELAY_SECS")
+                .map(|v| Duration::from_secs(v.parse().expect("SHUTDOWN_READINESS_DELAY_SECS must be a number")))
+                .unwrap_or(defaults.shutdown_readiness_delay),
+            shutdown_drain_timeout: env::var("SHUTDOWN_DRAIN_TIMEOUT_SECS")
+                .map(|v| Duration::from_secs(v.parse().expect("SHUTDOWN_DRAIN_TIMEOUT_SECS must be a number")))
+                .unwrap_or(defaults.shutdown_drain_timeout),
+            watch_storage: env::var("WATCH_STORAGE").map(|v| v != "false" && v != "0").unwrap_or(defaults.watch_storage),
+            collections_poll_interval: optional_secs_var("COLLECTIONS_POLL_SECS")
+                .unwrap_or(defaults.collections_poll_interval),
+            cache_max_bytes: env::var("CACHE_MAX_BYTES")
+                .map(|v| v.parse().expect("CACHE_MAX_BYTES must be a number"))
+                .unwrap_or(defaults.cache_max_bytes),
+            cache_ttl: optional_secs_var("CACHE_TTL_SECS").unwrap_or(defaults.cache_ttl),
+            cache_tti: optional_secs_var("CACHE_TTI_SECS").unwrap_or(defaults.cache_tti),
+            warmup: non_empty_var("WARMUP")
+                .map(|v| v.parse().unwrap_or_else(|e| panic!("WARMUP: {}", e)))
+                .unwrap_or(defaults.warmup),
+            warmup_concurrency: env::var("WARMUP_CONCURRENCY")
+                .map(|v| v.parse().expect("WARMUP_CONCURRENCY must be a number"))
+                .unwrap_or(defaults.warmup_concurrency),
         }
     }
 
@@ -26,3 +223,32 @@ impl Config {
         format!("{}:{}", self.host, self.port)
     }
 }
+
+fn non_empty_var(key: &str) -> Option<String> {
+    env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+/// Reads a duration in seconds where `0` means disabled.
+fn optional_secs_var(key: &str) -> Option<Option<Duration>> {
+    let secs: u64 = non_empty_var(key)?
+        .parse()
+        .unwrap_or_else(|_| panic!("{} must be a number", key));
+    Some((secs > 0).then(|| Duration::from_secs(secs)))
+}
+
+/// Reads an optional rate limit; `off` disables the limit.
+fn rate_limit_var(key: &str) -> Option<Option<RateLimit>> {
+    let value = non_empty_var(key)?;
+    if value.eq_ignore_ascii_case("off") {
+        return Some(None);
+    }
+    Some(Some(value.parse().unwrap_or_else(|e| panic!("{}: {}", key, e))))
+}
+
+/// Accepts CIDR notation or a bare address.
+fn parse_net(value: &str) -> Option<IpNet> {
+    value
+        .parse()
+        .ok()
+        .or_else(|| value.parse::<std::net::IpAddr>().ok().map(IpNet::from))
+}
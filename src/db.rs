@@ -1,3 +1,4 @@
+use sqlx::migrate::Migrator;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use sqlx::{Pool, Sqlite};
 use std::str::FromStr;
@@ -5,6 +6,9 @@ use std::time::Duration;
 
 pub type DbPool = Pool<Sqlite>;
 
+/// Migrations embedded at compile time.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
 pub async fn establish_connection(database_url: &str) -> Result<DbPool, sqlx::Error> {
     let options = SqliteConnectOptions::from_str(database_url)?
         .create_if_missing(true)
@@ -21,7 +25,21 @@ pub async fn establish_connection(database_url: &str) -> Result<DbPool, sqlx::Er
 }
 
 pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
-    sqlx::migrate!("./migrations").run(pool).await?;
+    MIGRATOR.run(pool).await?;
     tracing::info!("Database migrations completed");
     Ok(())
 }
+
+/// Versions of embedded migrations that have not been applied successfully.
+pub async fn pending_migrations(pool: &DbPool) -> Result<Vec<i64>, sqlx::Error> {
+    let applied: Vec<i64> = sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = 1")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| m.version)
+        .filter(|version| !applied.contains(version))
+        .collect())
+}
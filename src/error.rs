@@ -1,9 +1,10 @@
 use axum::{
-    http::StatusCode,
+    http::{header::RETRY_AFTER, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde::Serialize;
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -23,8 +24,39 @@ pub enum AppError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    /// Unknown or malformed query parameters, such as an unknown sort field.
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Rate limit exceeded, retry after {retry_after}s")]
+    RateLimited { retry_after: u64 },
+
+    #[error("Invalid content: {0}")]
+    InvalidContent(String),
+
     #[error("Task join error: {0}")]
     TaskJoin(String),
+
+    /// A server misconfiguration detected while handling a request.
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    /// An error handed to several callers, e.g. every request waiting on
+    /// the same collection load.
+    #[error(transparent)]
+    Shared(Arc<AppError>),
 }
 
 #[derive(Serialize)]
@@ -33,10 +65,36 @@ struct ErrorBody {
     message: String,
 }
 
+impl From<Arc<AppError>> for AppError {
+    fn from(error: Arc<AppError>) -> Self {
+        AppError::Shared(error)
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, code, message) = match &self {
+        self.render()
+    }
+}
+
+impl AppError {
+    fn render(&self) -> Response {
+        let (status, code, message) = match self {
+            AppError::Shared(inner) => return inner.render(),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "NOT_FOUND", msg.clone()),
+            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "BAD_REQUEST", msg.clone()),
+            AppError::InvalidQuery(msg) => (StatusCode::BAD_REQUEST, "INVALID_QUERY", msg.clone()),
+            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED", msg.clone()),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, "FORBIDDEN", msg.clone()),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, "CONFLICT", msg.clone()),
+            AppError::RateLimited { retry_after } => {
+                let body = ErrorBody {
+                    code: "RATE_LIMITED",
+                    message: format!("Too many requests, retry after {} seconds", retry_after),
+                };
+                return (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, retry_after.to_string())], Json(body))
+                    .into_response();
+            }
             AppError::Database(e) => {
                 tracing::error!(error = %e, "Database error");
                 (StatusCode::INTERNAL_SERVER_ERROR, "DATABASE_ERROR", "Internal database error".into())
@@ -53,10 +111,18 @@ impl IntoResponse for AppError {
                 tracing::error!(error = %e, "Serialization error");
                 (StatusCode::INTERNAL_SERVER_ERROR, "SERIALIZATION_ERROR", "Data processing failed".into())
             }
+            AppError::InvalidContent(msg) => {
+                tracing::error!(error = %msg, "Invalid stored content");
+                (StatusCode::INTERNAL_SERVER_ERROR, "INVALID_CONTENT", msg.clone())
+            }
             AppError::TaskJoin(msg) => {
                 tracing::error!(error = %msg, "Task join error");
                 (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR", "Internal error".into())
             }
+            AppError::Internal(msg) => {
+                tracing::error!(error = %msg, "Internal error");
+                (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR", "Internal error".into())
+            }
         };
 
         (status, Json(ErrorBody { code, message })).into_response()
@@ -4,33 +4,70 @@ pub mod error;
 pub mod middleware;
 pub mod modules;
 
-use axum::Router;
+use axum::{middleware::from_fn_with_state, Router};
+use config::Config;
 use db::DbPool;
+use middleware::RateLimits;
+use modules::auth::{ApiKeyStore, JwtVerifier};
 use modules::content::ContentStore;
-use std::path::PathBuf;
+use modules::health::Lifecycle;
+use modules::metrics::Metrics;
+use modules::search::SearchIndex;
 use std::sync::Arc;
 
 pub struct AppState {
+    pub config: Config,
     pub db: DbPool,
     pub content_store: ContentStore,
+    pub search: SearchIndex,
+    pub api_keys: ApiKeyStore,
+    pub jwt: Option<JwtVerifier>,
+    pub rate_limits: RateLimits,
+    pub metrics: Metrics,
+    pub lifecycle: Lifecycle,
 }
 
 impl AppState {
-    pub fn new(db: DbPool, storage_path: PathBuf) -> Self {
-        Self {
-            content_store: ContentStore::new(storage_path, db.clone()),
+    /// Builds the shared state. Fails when the configuration is invalid,
+    /// e.g. an unreadable JWKS file.
+    pub fn new(db: DbPool, config: Config) -> Result<Self, String> {
+        let metrics = Metrics::new();
+        Ok(Self {
+            content_store: ContentStore::new(&config, db.clone(), metrics.clone()),
+            search: SearchIndex::new(db.clone()),
+            api_keys: ApiKeyStore::new(db.clone()),
+            jwt: JwtVerifier::from_config(&config)?,
+            rate_limits: RateLimits::from_config(&config),
+            metrics,
+            lifecycle: Lifecycle::default(),
             db,
-        }
+            config,
+        })
     }
 }
 
 pub fn create_router(state: Arc<AppState>) -> Router {
     let (request_id_layer, propagate_layer) = middleware::request_id();
 
+    let api = Router::new()
+        .nest(
+            "/api/v1/content",
+            modules::content::routes().merge(modules::search::routes()),
+        )
+        .nest("/api/v1/admin", modules::admin::routes())
+        .merge(modules::metrics::routes())
+        .layer(from_fn_with_state(state.clone(), middleware::rate_limit))
+        .layer(from_fn_with_state(state.clone(), middleware::authenticate));
+
+    // Health probes bypass authentication and rate limits, so an
+    // orchestrator polling from one address is never turned away.
     Router::new()
-        .nest("/api/v1/content", modules::content::routes())
-        .layer(middleware::rate_limit())
-        .layer(middleware::cors())
+        .merge(api)
+        .merge(modules::health::routes())
+        .layer(middleware::cors(&state.config))
+        .layer(middleware::compression(&state.config))
+        .layer(from_fn_with_state(state.clone(), middleware::track_metrics))
+        .layer(from_fn_with_state(state.clone(), middleware::track_in_flight))
         .layer(propagate_layer)
         .layer(middleware::trace())
         .layer(request_id_layer)
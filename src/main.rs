@@ -1,7 +1,11 @@
+use tulpar_api::modules::content::{warmup, Reloader};
+use tulpar_api::modules::health::lifecycle::shutdown_signal;
 use tulpar_api::{config::Config, create_router, db, AppState};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::net::TcpListener;
-use tracing::info;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 #[tokio::main]
@@ -18,13 +22,63 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     db::run_migrations(&pool).await?;
 
     let addr = config.socket_addr();
-    let state = Arc::new(AppState::new(pool, config.storage_path));
-    let app = create_router(state);
+    let state = Arc::new(AppState::new(pool, config)?);
+    let app = create_router(state.clone());
+    let _reloader = Reloader::spawn(state.clone())?;
+    warmup::spawn(state.clone());
+    let access_flush = warmup::spawn_access_flush(state.clone());
 
     let listener = TcpListener::bind(&addr).await?;
     info!(addr = %addr, "Listening");
 
-    axum::serve(listener, app).await?;
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let mut server = tokio::spawn(async move {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async {
+                let _ = stop_rx.await;
+            })
+            .await
+    });
+
+    tokio::select! {
+        result = &mut server => {
+            result??;
+            return Ok(());
+        }
+        _ = shutdown_signal() => {}
+    }
+
+    // Fail readiness first so load balancers stop sending traffic, then stop
+    // accepting connections and give in-flight requests time to finish.
+    state.lifecycle.begin_drain();
+    let delay = state.config.shutdown_readiness_delay;
+    info!(
+        in_flight = state.lifecycle.in_flight_count(),
+        readiness_delay_secs = delay.as_secs(),
+        "Shutting down, readiness now failing"
+    );
+    tokio::time::sleep(delay).await;
+    let _ = stop_tx.send(());
+
+    let drain_timeout = state.config.shutdown_drain_timeout;
+    match tokio::time::timeout(drain_timeout, &mut server).await {
+        Ok(result) => {
+            result??;
+            info!("All connections drained");
+        }
+        Err(_) => {
+            warn!(timeout_secs = drain_timeout.as_secs(), "Drain timeout expired, aborting remaining requests");
+            state.lifecycle.log_in_flight();
+            server.abort();
+        }
+    }
+
+    access_flush.abort();
+    if let Err(e) = state.content_store.flush_access().await {
+        warn!(error = %e, "Failed to record collection access");
+    }
+    state.db.close().await;
+    info!(requests = state.lifecycle.total_requests(), "Shutdown complete");
 
     Ok(())
 }
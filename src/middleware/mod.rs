@@ -1,58 +1,198 @@
-use axum::{body::Body, extract::ConnectInfo, http::{Method, Request}};
-use governor::{
-    clock::QuantaInstant,
-    middleware::NoOpMiddleware,
+mod rate_limit;
+
+pub use rate_limit::{rate_limit, RateLimits, RouteGroup};
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, State},
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        Extensions, HeaderMap, HeaderName, HeaderValue, Method, Request,
+    },
+    middleware::Next,
+    response::Response,
 };
 use std::sync::Arc;
-use tower_governor::{
-    governor::GovernorConfigBuilder,
-    key_extractor::KeyExtractor,
-    GovernorLayer,
-};
+use std::time::Instant;
 use tower_http::{
-    cors::{Any, CorsLayer},
+    compression::{
+        predicate::{NotForContentType, SizeAbove},
+        CompressionLayer, Predicate,
+    },
+    cors::{AllowOrigin, Any, CorsLayer},
     request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     trace::TraceLayer,
 };
 use tracing::Level;
 
-#[derive(Clone, Copy)]
-pub struct SmartIpKeyExtractor;
-
-impl KeyExtractor for SmartIpKeyExtractor {
-    type Key = std::net::IpAddr;
-
-    fn extract<B>(&self, req: &Request<B>) -> Result<Self::Key, tower_governor::errors::GovernorError> {
-        req.extensions()
-            .get::<ConnectInfo<std::net::SocketAddr>>()
-            .map(|ConnectInfo(addr)| addr.ip())
-            .or_else(|| {
-                req.headers()
-                    .get("x-forwarded-for")
-                    .and_then(|v| v.to_str().ok())
-                    .and_then(|v| v.split(',').next())
-                    .and_then(|v| v.trim().parse().ok())
-            })
-            .ok_or(tower_governor::errors::GovernorError::UnableToExtractKey)
+use crate::config::Config;
+use crate::modules::content::encoding::Encoding;
+use crate::error::AppError;
+use crate::modules::auth::jwt::looks_like_jwt;
+use crate::modules::auth::{Claims, Principal, Scope};
+use crate::AppState;
+
+/// Resolves the caller from `Authorization: Bearer <key or JWT>` or
+/// `X-API-Key` and stores it as a `Principal` request extension, along with
+/// the `Claims` of a verified JWT. Requests without credentials continue
+/// anonymously; invalid credentials are rejected and count against the
+/// client's failed authentication limit, which is checked before any key
+/// lookup or token verification.
+pub async fn authenticate(
+    State(state): State<Arc<AppState>>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let principal = match credentials(req.headers()) {
+        Some(token) => {
+            let ip = state.rate_limits.request_ip(&req)?;
+            if let Some(ip) = ip {
+                state.rate_limits.check_blocked(ip)?;
+            }
+            match verify_credentials(&state, token).await {
+                Ok((principal, claims)) => {
+                    if let Some(claims) = claims {
+                        req.extensions_mut().insert(claims);
+                    }
+                    principal
+                }
+                Err(e) => {
+                    if let Some(ip) = ip {
+                        state.rate_limits.record_failure(ip)?;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        None => Principal::anonymous(if state.config.public_read {
+            vec![Scope::Read]
+        } else {
+            Vec::new()
+        }),
+    };
+
+    req.extensions_mut().insert(principal);
+    Ok(next.run(req).await)
+}
+
+async fn verify_credentials(state: &AppState, token: &str) -> Result<(Principal, Option<Claims>), AppError> {
+    if looks_like_jwt(token) {
+        let verifier = state
+            .jwt
+            .as_ref()
+            .ok_or_else(|| AppError::Unauthorized("Bearer tokens are not accepted".into()))?;
+        let claims = verifier.verify(token)?;
+        return Ok((claims.principal(), Some(claims)));
     }
+    let principal = state
+        .api_keys
+        .authenticate(token)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid API key".into()))?;
+    Ok((principal, None))
+}
+
+fn credentials(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-api-key").and_then(|v| v.to_str().ok()))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+/// Counts requests and records their latency by method, matched route and
+/// status. Requests that matched no route share the `unmatched` label.
+pub async fn track_metrics(
+    State(state): State<Arc<AppState>>,
+    matched: Option<MatchedPath>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let method = req.method().clone();
+    let route = matched
+        .as_ref()
+        .map_or("unmatched", |path| path.as_str())
+        .to_string();
+    let started = Instant::now();
+
+    let response = next.run(req).await;
 
-    fn name(&self) -> &'static str {
-        "SmartIpKeyExtractor"
+    let status = response.status().as_u16().to_string();
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    state.metrics.http_requests.with_label_values(&labels).inc();
+    state
+        .metrics
+        .http_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+    response
+}
+
+/// Registers the request with the lifecycle so shutdown can report what was
+/// still running when the drain timeout expired.
+pub async fn track_in_flight(
+    State(state): State<Arc<AppState>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let _guard = state.lifecycle.track(req.method().as_str(), req.uri().path());
+    next.run(req).await
+}
+
+/// Any origin may read; write methods are only offered to the origins listed
+/// in `CORS_ALLOWED_ORIGINS`.
+pub fn cors(config: &Config) -> CorsLayer {
+    let layer = CorsLayer::new()
+        .allow_headers([AUTHORIZATION, CONTENT_TYPE, HeaderName::from_static("x-api-key")])
+        .max_age(std::time::Duration::from_secs(3600));
+
+    if config.cors_allowed_origins.is_empty() {
+        return layer
+            .allow_origin(Any)
+            .allow_methods([Method::GET, Method::HEAD, Method::OPTIONS]);
     }
+
+    let origins: Vec<HeaderValue> = config
+        .cors_allowed_origins
+        .iter()
+        .filter_map(|origin| origin.parse().ok())
+        .collect();
+
+    layer.allow_origin(AllowOrigin::list(origins)).allow_methods([
+        Method::GET,
+        Method::HEAD,
+        Method::POST,
+        Method::PUT,
+        Method::PATCH,
+        Method::DELETE,
+        Method::OPTIONS,
+    ])
 }
 
-pub fn cors() -> CorsLayer {
-    CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::DELETE,
-            Method::OPTIONS,
-        ])
-        .allow_headers(Any)
-        .max_age(std::time::Duration::from_secs(3600))
+/// Response extension that keeps the compression layer away from a response,
+/// e.g. because the handler already chose its encoding.
+#[derive(Clone, Copy, Debug)]
+pub struct NoCompression;
+
+/// Compresses responses with the configured algorithms. Responses that are
+/// below `compression_min_size`, already carry a `Content-Encoding`, or are
+/// marked with `NoCompression` pass through untouched.
+pub fn compression(config: &Config) -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(config.compression_min_size)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE)
+        .and(|_, _, _: &HeaderMap, extensions: &Extensions| extensions.get::<NoCompression>().is_none());
+
+    let enabled = |encoding| config.compression.contains(&encoding);
+    CompressionLayer::new()
+        .br(enabled(Encoding::Brotli))
+        .zstd(enabled(Encoding::Zstd))
+        .gzip(enabled(Encoding::Gzip))
+        .deflate(enabled(Encoding::Deflate))
+        .compress_when(predicate)
 }
 
 pub fn trace() -> TraceLayer<
@@ -83,16 +223,3 @@ pub fn request_id() -> (SetRequestIdLayer<MakeRequestUuid>, PropagateRequestIdLa
         PropagateRequestIdLayer::x_request_id(),
     )
 }
-
-pub fn rate_limit() -> GovernorLayer<SmartIpKeyExtractor, NoOpMiddleware<QuantaInstant>> {
-    let governor_conf = GovernorConfigBuilder::default()
-        .key_extractor(SmartIpKeyExtractor)
-        .per_second(2)
-        .burst_size(5)
-        .finish()
-        .expect("Failed to create governor config");
-    
-    GovernorLayer {
-        config: Arc::new(governor_conf),
-    }
-}
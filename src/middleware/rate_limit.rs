@@ -0,0 +1,309 @@
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, MatchedPath, State},
+    http::{HeaderMap, HeaderValue, Method, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use governor::{
+    clock::{Clock, DefaultClock},
+    middleware::StateInformationMiddleware,
+    state::keyed::DefaultKeyedStateStore,
+    Quota, RateLimiter,
+};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, RateLimit};
+use crate::error::AppError;
+use crate::modules::auth::Principal;
+use crate::AppState;
+
+type KeyedLimiter =
+    RateLimiter<String, DefaultKeyedStateStore<String>, DefaultClock, StateInformationMiddleware>;
+
+/// Stale limiter state is dropped after this many checks.
+const RETAIN_INTERVAL: u64 = 1024;
+
+/// Routes that share a rate limit, selected by `RATE_LIMIT_GROUPS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteGroup {
+    Read,
+    Search,
+    Write,
+    Admin,
+}
+
+impl RouteGroup {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RouteGroup::Read => "read",
+            RouteGroup::Search => "search",
+            RouteGroup::Write => "write",
+            RouteGroup::Admin => "admin",
+        }
+    }
+
+    /// Classifies a request by the route it matched, so that content named
+    /// like a route (a collection aliased `admin`) cannot change its group:
+    /// admin routes first, then search, then any non-safe method as a write.
+    pub fn classify(method: &Method, route: Option<&str>) -> Self {
+        match route {
+            Some(route) if route.starts_with("/api/v1/admin/") => RouteGroup::Admin,
+            Some("/api/v1/content/search") => RouteGroup::Search,
+            _ if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) => RouteGroup::Read,
+            _ => RouteGroup::Write,
+        }
+    }
+}
+
+impl FromStr for RouteGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "read" => Ok(RouteGroup::Read),
+            "search" => Ok(RouteGroup::Search),
+            "write" => Ok(RouteGroup::Write),
+            "admin" => Ok(RouteGroup::Admin),
+            other => Err(format!("Unknown route group: {}", other)),
+        }
+    }
+}
+
+/// State of the tightest limiter a request went through, reported in the
+/// `RateLimit-*` headers.
+struct Allowance {
+    limit: u32,
+    remaining: u32,
+    reset: Duration,
+}
+
+/// Keyed limiters built from `Config`. Anonymous callers are keyed by client
+/// IP, authenticated ones by their API key or token subject; route group
+/// limits apply on top with the same key. Failed authentications are
+/// limited per client IP with the anonymous quota.
+pub struct RateLimits {
+    ip: Option<KeyedLimiter>,
+    key: Option<KeyedLimiter>,
+    groups: Vec<(RouteGroup, KeyedLimiter)>,
+    failures: Option<KeyedLimiter>,
+    /// Clients that spent their failed authentication budget, with the time
+    /// they may try again.
+    blocked: Mutex<HashMap<IpAddr, Instant>>,
+    trusted_proxies: Vec<IpNet>,
+    checks: AtomicU64,
+}
+
+impl RateLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            ip: config.rate_limit_ip.map(limiter),
+            key: config.rate_limit_key.map(limiter),
+            groups: config
+                .rate_limit_groups
+                .iter()
+                .map(|&(group, limit)| (group, limiter(limit)))
+                .collect(),
+            failures: config.rate_limit_ip.map(limiter),
+            blocked: Mutex::new(HashMap::new()),
+            trusted_proxies: config.trusted_proxies.clone(),
+            checks: AtomicU64::new(0),
+        }
+    }
+
+    /// Client address for a connection from `peer`. `X-Forwarded-For` is only
+    /// consulted when the peer is a trusted proxy; the header is read right to
+    /// left and the first hop that is not itself a trusted proxy wins.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+
+        let hops: Vec<IpAddr> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .filter_map(|hop| hop.trim().parse().ok())
+            .collect();
+
+        hops.iter()
+            .rev()
+            .find(|hop| !self.is_trusted(**hop))
+            .or(hops.first())
+            .copied()
+            .unwrap_or(peer)
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Address of the client that sent `req`. Requests served without a
+    /// socket address (e.g. in-process tests) have none; they are refused
+    /// while any limit is keyed by IP rather than sharing one bucket.
+    pub fn request_ip(&self, req: &Request<Body>) -> Result<Option<IpAddr>, AppError> {
+        match req.extensions().get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => Ok(Some(self.client_ip(addr.ip(), req.headers()))),
+            None if self.ip.is_some() || !self.groups.is_empty() => Err(AppError::Internal(
+                "Client address unavailable; serve with connect info to apply IP rate limits".into(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Rejects a client that recently spent its failed authentication
+    /// budget, before its credentials are checked.
+    pub fn check_blocked(&self, ip: IpAddr) -> Result<(), AppError> {
+        let mut blocked = self.blocked.lock().unwrap();
+        match blocked.get(&ip).map(|until| until.saturating_duration_since(Instant::now())) {
+            Some(wait) if !wait.is_zero() => Err(AppError::RateLimited {
+                retry_after: ceil_secs(wait),
+            }),
+            Some(_) => {
+                blocked.remove(&ip);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Charges a failed authentication to `ip`. Once the budget is spent the
+    /// client is rate limited until it refills.
+    pub fn record_failure(&self, ip: IpAddr) -> Result<(), AppError> {
+        let Some(limiter) = &self.failures else {
+            return Ok(());
+        };
+        match limiter.check_key(&ip.to_string()) {
+            Ok(_) => Ok(()),
+            Err(not_until) => {
+                let wait = not_until.wait_time_from(limiter.clock().now());
+                self.blocked.lock().unwrap().insert(ip, Instant::now() + wait);
+                Err(AppError::RateLimited {
+                    retry_after: ceil_secs(wait),
+                })
+            }
+        }
+    }
+
+    /// Checks every limiter that applies to the request. A rejection carries
+    /// the exhausted limiter's state with `reset` set to the wait time.
+    fn check(&self, key: String, group: RouteGroup, authenticated: bool) -> Result<Option<Allowance>, Allowance> {
+        if self.checks.fetch_add(1, Ordering::Relaxed) % RETAIN_INTERVAL == RETAIN_INTERVAL - 1 {
+            self.retain_recent();
+        }
+
+        let client = if authenticated { self.key.as_ref() } else { self.ip.as_ref() };
+        let limiters = client.into_iter().chain(
+            self.groups
+                .iter()
+                .filter(|(g, _)| *g == group)
+                .map(|(_, limiter)| limiter),
+        );
+
+        let mut tightest: Option<Allowance> = None;
+        for limiter in limiters {
+            let allowance = match limiter.check_key(&key) {
+                Ok(snapshot) => {
+                    let quota = snapshot.quota();
+                    let limit = quota.burst_size().get();
+                    let remaining = snapshot.remaining_burst_capacity();
+                    Allowance {
+                        limit,
+                        remaining,
+                        reset: quota.replenish_interval() * (limit - remaining),
+                    }
+                }
+                Err(not_until) => {
+                    return Err(Allowance {
+                        limit: not_until.quota().burst_size().get(),
+                        remaining: 0,
+                        reset: not_until.wait_time_from(limiter.clock().now()),
+                    });
+                }
+            };
+            if tightest.as_ref().is_none_or(|t| allowance.remaining < t.remaining) {
+                tightest = Some(allowance);
+            }
+        }
+        Ok(tightest)
+    }
+
+    fn retain_recent(&self) {
+        let limiters = self.ip.iter().chain(&self.key).chain(&self.failures);
+        for limiter in limiters.chain(self.groups.iter().map(|(_, l)| l)) {
+            limiter.retain_recent();
+            limiter.shrink_to_fit();
+        }
+        let now = Instant::now();
+        self.blocked.lock().unwrap().retain(|_, until| *until > now);
+    }
+}
+
+fn limiter(limit: RateLimit) -> KeyedLimiter {
+    let per_second = NonZeroU32::new(limit.per_second).expect("rate limit must be positive");
+    let burst = NonZeroU32::new(limit.burst).expect("rate limit burst must be positive");
+    RateLimiter::keyed(Quota::per_second(per_second).allow_burst(burst))
+        .with_middleware::<StateInformationMiddleware>()
+}
+
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+/// Enforces the configured limits. Runs after `authenticate` so callers with
+/// credentials are limited per key rather than per IP; requests with invalid
+/// credentials are limited by `authenticate` itself.
+pub async fn rate_limit(
+    State(state): State<Arc<AppState>>,
+    matched: Option<MatchedPath>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let limits = &state.rate_limits;
+    let principal = req.extensions().get::<Principal>();
+    let key = match principal.filter(|p| p.authenticated) {
+        Some(principal) => format!("key:{}", principal.subject),
+        None => match limits.request_ip(&req) {
+            Ok(Some(ip)) => format!("ip:{}", ip),
+            Ok(None) => return next.run(req).await,
+            Err(e) => return e.into_response(),
+        },
+    };
+    let group = RouteGroup::classify(req.method(), matched.as_ref().map(MatchedPath::as_str));
+    let authenticated = principal.is_some_and(|p| p.authenticated);
+
+    match limits.check(key.clone(), group, authenticated) {
+        Ok(allowance) => {
+            let mut response = next.run(req).await;
+            if let Some(allowance) = allowance {
+                allowance.apply(response.headers_mut());
+            }
+            response
+        }
+        Err(denied) => {
+            tracing::debug!(key = %key, group = group.as_str(), "Rate limit exceeded");
+            let mut response = AppError::RateLimited {
+                retry_after: ceil_secs(denied.reset),
+            }
+            .into_response();
+            denied.apply(response.headers_mut());
+            response
+        }
+    }
+}
+
+impl Allowance {
+    fn apply(&self, headers: &mut HeaderMap) {
+        headers.insert("ratelimit-limit", HeaderValue::from(self.limit));
+        headers.insert("ratelimit-remaining", HeaderValue::from(self.remaining));
+        headers.insert("ratelimit-reset", HeaderValue::from(ceil_secs(self.reset)));
+    }
+}
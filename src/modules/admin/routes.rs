@@ -0,0 +1,146 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::AppError;
+use crate::modules::auth::{Principal, Scope};
+use crate::modules::content::store::CacheWeight;
+use crate::AppState;
+
+/// Collections loaded concurrently by a warm request.
+const WARM_CONCURRENCY: usize = 4;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/cache", get(list_cache).delete(flush_cache))
+        .route("/cache/:alias", delete(evict_collection))
+        .route("/cache/warm", post(warm_cache))
+}
+
+#[derive(Serialize)]
+struct CacheEntry {
+    alias: String,
+    revision: i64,
+    content_hash: String,
+    items: usize,
+    size_bytes: u64,
+    age_secs: u64,
+}
+
+#[derive(Serialize)]
+struct CacheListing {
+    entries: usize,
+    #[serde(flatten)]
+    weight: CacheWeight,
+    collections: Vec<CacheEntry>,
+}
+
+#[derive(Deserialize)]
+struct WarmRequest {
+    aliases: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct WarmFailure {
+    alias: String,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct WarmResponse {
+    loaded: Vec<String>,
+    failed: Vec<WarmFailure>,
+}
+
+/// Records an administrative action under the `audit` tracing target.
+fn audit(principal: &Principal, action: &str, detail: &str) {
+    tracing::info!(target: "audit", subject = %principal.subject, action, detail, "Admin action");
+}
+
+async fn list_cache(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<CacheListing>, AppError> {
+    principal.require(Scope::Admin)?;
+
+    let mut collections: Vec<CacheEntry> = state
+        .content_store
+        .cached()
+        .await
+        .into_iter()
+        .map(|entry| CacheEntry {
+            alias: entry.meta.alias.clone(),
+            revision: entry.meta.revision,
+            content_hash: entry.content_hash.clone(),
+            items: entry.collection.items.len(),
+            size_bytes: entry.size,
+            age_secs: entry.loaded_at.elapsed().as_secs(),
+        })
+        .collect();
+    collections.sort_by(|a, b| a.alias.cmp(&b.alias));
+
+    audit(&principal, "cache.list", "");
+    Ok(Json(CacheListing {
+        entries: collections.len(),
+        weight: state.content_store.cache_weight().await,
+        collections,
+    }))
+}
+
+async fn evict_collection(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Path(alias): Path<String>,
+) -> Result<StatusCode, AppError> {
+    principal.require(Scope::Admin)?;
+    if !state.content_store.evict(&alias).await {
+        return Err(AppError::NotFound(format!("Collection is not cached: {}", alias)));
+    }
+    audit(&principal, "cache.evict", &alias);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn flush_cache(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+) -> Result<StatusCode, AppError> {
+    principal.require(Scope::Admin)?;
+    state.content_store.clear().await;
+    audit(&principal, "cache.flush", "");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn warm_cache(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<WarmRequest>,
+) -> Result<Json<WarmResponse>, AppError> {
+    principal.require(Scope::Admin)?;
+
+    let mut response = WarmResponse {
+        loaded: Vec::new(),
+        failed: Vec::new(),
+    };
+    for (alias, result) in state.content_store.warm(request.aliases, WARM_CONCURRENCY).await {
+        match result {
+            Ok(()) => response.loaded.push(alias),
+            Err(e) => response.failed.push(WarmFailure {
+                alias,
+                error: e.to_string(),
+            }),
+        }
+    }
+    response.loaded.sort();
+
+    audit(
+        &principal,
+        "cache.warm",
+        &format!("loaded={} failed={}", response.loaded.len(), response.failed.len()),
+    );
+    Ok(Json(response))
+}
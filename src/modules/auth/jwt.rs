@@ -0,0 +1,130 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+
+use super::principal::Principal;
+use crate::config::Config;
+use crate::error::AppError;
+
+/// Claims of a verified JWT. `scope` is a space-separated list of scopes and
+/// `collections` the aliases the token grants access to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: u64,
+    #[serde(default)]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub collections: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Claims {
+    pub fn principal(&self) -> Principal {
+        Principal {
+            subject: format!("jwt:{}", self.sub),
+            scopes: self
+                .scope
+                .as_deref()
+                .unwrap_or_default()
+                .split_whitespace()
+                .filter_map(|s| s.parse().ok())
+                .collect(),
+            collections: Some(self.collections.clone().unwrap_or_default()),
+            authenticated: true,
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Claims {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Claims>()
+            .cloned()
+            .ok_or_else(|| AppError::Unauthorized("A bearer token is required".into()))
+    }
+}
+
+/// Verifies HS256 tokens against a shared secret and RS256 tokens against a
+/// local JWKS file.
+pub struct JwtVerifier {
+    secret: Option<DecodingKey>,
+    jwks: Option<JwkSet>,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl JwtVerifier {
+    /// Returns `None` when neither a secret nor a JWKS file is configured.
+    pub fn from_config(config: &Config) -> Result<Option<Self>, String> {
+        let jwks = match &config.jwt_jwks_path {
+            Some(path) => {
+                let data = std::fs::read(path)
+                    .map_err(|e| format!("Failed to read JWKS file {:?}: {}", path, e))?;
+                Some(serde_json::from_slice(&data).map_err(|e| format!("Invalid JWKS file {:?}: {}", path, e))?)
+            }
+            None => None,
+        };
+        let secret = config
+            .jwt_secret
+            .as_ref()
+            .map(|secret| DecodingKey::from_secret(secret.as_bytes()));
+
+        if secret.is_none() && jwks.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            secret,
+            jwks,
+            issuer: config.jwt_issuer.clone(),
+            audience: config.jwt_audience.clone(),
+        }))
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, AppError> {
+        let invalid = |reason: &str| AppError::Unauthorized(format!("Invalid token: {}", reason));
+
+        let header = decode_header(token).map_err(|e| invalid(&e.to_string()))?;
+        let key = match header.alg {
+            Algorithm::HS256 => self.secret.clone().ok_or_else(|| invalid("HS256 is not accepted"))?,
+            Algorithm::RS256 => {
+                let jwks = self.jwks.as_ref().ok_or_else(|| invalid("RS256 is not accepted"))?;
+                let jwk = match &header.kid {
+                    Some(kid) => jwks.find(kid),
+                    None if jwks.keys.len() == 1 => jwks.keys.first(),
+                    None => None,
+                }
+                .ok_or_else(|| invalid("unknown key id"))?;
+                DecodingKey::from_jwk(jwk).map_err(|e| invalid(&e.to_string()))?
+            }
+            other => return Err(invalid(&format!("unsupported algorithm {:?}", other))),
+        };
+
+        let mut validation = Validation::new(header.alg);
+        match &self.issuer {
+            Some(issuer) => validation.set_issuer(&[issuer]),
+            None => validation.iss = None,
+        }
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        decode::<Claims>(token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| invalid(&e.to_string()))
+    }
+}
+
+/// Compact JWS tokens have exactly three dot-separated segments; API keys
+/// have none.
+pub fn looks_like_jwt(token: &str) -> bool {
+    token.split('.').count() == 3
+}
@@ -0,0 +1,100 @@
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::principal::{format_scopes, Principal, Scope};
+use crate::db::DbPool;
+use crate::error::AppError;
+
+const KEY_PREFIX: &str = "tlp_";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ApiKeyRecord {
+    pub id: i64,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: String,
+    pub created_at: String,
+    pub revoked_at: Option<String>,
+}
+
+/// API keys are random tokens shown once at creation; only their SHA-256
+/// hash is stored.
+#[derive(Clone)]
+pub struct ApiKeyStore {
+    db: DbPool,
+}
+
+impl ApiKeyStore {
+    pub fn new(db: DbPool) -> Self {
+        Self { db }
+    }
+
+    /// Mints a new key and returns its record along with the plaintext key.
+    pub async fn create(&self, name: &str, scopes: &[Scope]) -> Result<(ApiKeyRecord, String), AppError> {
+        if scopes.is_empty() {
+            return Err(AppError::BadRequest("An API key needs at least one scope".into()));
+        }
+
+        let mut secret = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let key = format!("{}{}", KEY_PREFIX, hex::encode(secret));
+
+        let record: ApiKeyRecord = sqlx::query_as(
+            "INSERT INTO api_keys (name, key_prefix, key_hash, scopes) VALUES (?, ?, ?, ?) \
+             RETURNING id, name, key_prefix, scopes, created_at, revoked_at",
+        )
+        .bind(name)
+        .bind(&key[..KEY_PREFIX.len() + 8])
+        .bind(hash_key(&key))
+        .bind(format_scopes(scopes))
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok((record, key))
+    }
+
+    pub async fn revoke(&self, id: i64) -> Result<(), AppError> {
+        let result = sqlx::query("UPDATE api_keys SET revoked_at = datetime('now') WHERE id = ? AND revoked_at IS NULL")
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::NotFound(format!("Active API key not found: {}", id)));
+        }
+        Ok(())
+    }
+
+    pub async fn list(&self) -> Result<Vec<ApiKeyRecord>, AppError> {
+        let keys = sqlx::query_as(
+            "SELECT id, name, key_prefix, scopes, created_at, revoked_at FROM api_keys ORDER BY id",
+        )
+        .fetch_all(&self.db)
+        .await?;
+        Ok(keys)
+    }
+
+    /// Resolves a presented key to a principal, or `None` if the key is
+    /// unknown or revoked.
+    pub async fn authenticate(&self, key: &str) -> Result<Option<Principal>, AppError> {
+        let record: Option<ApiKeyRecord> = sqlx::query_as(
+            "SELECT id, name, key_prefix, scopes, created_at, revoked_at FROM api_keys \
+             WHERE key_hash = ? AND revoked_at IS NULL",
+        )
+        .bind(hash_key(key))
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(record.map(|record| Principal {
+            subject: format!("api-key:{}", record.id),
+            scopes: record.scopes.split(',').filter_map(|s| s.parse().ok()).collect(),
+            collections: None,
+            authenticated: true,
+        }))
+    }
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
@@ -0,0 +1,7 @@
+pub mod jwt;
+pub mod keys;
+pub mod principal;
+
+pub use jwt::{Claims, JwtVerifier};
+pub use keys::ApiKeyStore;
+pub use principal::{Principal, Scope};
@@ -0,0 +1,144 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::AppError;
+use crate::modules::content::store::CollectionMetadata;
+
+/// Permission levels, ordered so that a higher scope implies every lower one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Write,
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "read" => Ok(Scope::Read),
+            "write" => Ok(Scope::Write),
+            "admin" => Ok(Scope::Admin),
+            other => Err(format!("Unknown scope: {}", other)),
+        }
+    }
+}
+
+/// The caller of a request, resolved by `middleware::authenticate`.
+/// Anonymous callers carry whatever scopes the server grants publicly.
+///
+/// `collections` lists the aliases the caller may write to or read when
+/// private; `None` grants every collection and `"*"` is a wildcard.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub subject: String,
+    pub scopes: Vec<Scope>,
+    pub collections: Option<Vec<String>>,
+    pub authenticated: bool,
+}
+
+impl Principal {
+    pub fn anonymous(scopes: Vec<Scope>) -> Self {
+        Self {
+            subject: "anonymous".into(),
+            scopes,
+            collections: Some(Vec::new()),
+            authenticated: false,
+        }
+    }
+
+    pub fn can_access(&self, alias: &str) -> bool {
+        if self.has_scope(Scope::Admin) {
+            return true;
+        }
+        match &self.collections {
+            None => true,
+            Some(granted) => granted.iter().any(|g| g == "*" || g == alias),
+        }
+    }
+
+    /// Checks `scope` against a collection. Public collections only need the
+    /// scope for reads; writes and private collections also need a grant.
+    pub fn authorize(&self, scope: Scope, meta: &CollectionMetadata) -> Result<(), AppError> {
+        self.authorize_alias(scope, &meta.alias, meta.private)
+    }
+
+    pub fn authorize_alias(&self, scope: Scope, alias: &str, private: bool) -> Result<(), AppError> {
+        self.require(scope)?;
+        if (scope >= Scope::Write || private) && !self.can_access(alias) {
+            return Err(if self.authenticated {
+                AppError::Forbidden(format!("No access to collection: {}", alias))
+            } else {
+                AppError::Unauthorized("Authentication required".into())
+            });
+        }
+        Ok(())
+    }
+
+    pub fn can_read(&self, meta: &CollectionMetadata) -> bool {
+        self.authorize(Scope::Read, meta).is_ok()
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.iter().any(|granted| *granted >= scope)
+    }
+
+    pub fn require(&self, scope: Scope) -> Result<(), AppError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else if self.authenticated {
+            Err(AppError::Forbidden(format!("Missing required scope: {}", scope)))
+        } else {
+            Err(AppError::Unauthorized("Authentication required".into()))
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<Principal>()
+            .cloned()
+            .unwrap_or_else(|| Principal::anonymous(Vec::new())))
+    }
+}
+
+pub fn parse_scopes(value: &str) -> Result<Vec<Scope>, String> {
+    value
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(Scope::from_str)
+        .collect()
+}
+
+pub fn format_scopes(scopes: &[Scope]) -> String {
+    scopes
+        .iter()
+        .map(Scope::as_str)
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,303 @@
+//! Chunked collection files: a fixed header, an item index and independently
+//! LZ4-compressed blocks of items, so single items and pages can be read by
+//! seeking instead of decompressing the whole collection.
+//!
+//! ```text
+//! offset  size  field
+//! 0       4     magic "TLPC"
+//! 4       2     format version (u16, little-endian)
+//! 6       2     reserved
+//! 8       4     compressed index length (u32)
+//! 12      8     length of the collection JSON (u64)
+//! 20      32    SHA-256 of the collection JSON
+//! 52      ..    index: size-prepended LZ4 block holding the JSON `ChunkIndex`
+//! ..      ..    blocks: size-prepended LZ4 blocks of concatenated item JSON
+//! ```
+//!
+//! Block offsets in the index are relative to the end of the index. The
+//! collection JSON the hash and length refer to is the compact
+//! `serde_json` serialization of the whole collection, the same bytes a
+//! legacy `.json.lz4` file written by this server decompresses to.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ops::Range;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use super::store::{ContentCollection, ContentItem};
+use crate::error::AppError;
+
+pub const MAGIC: &[u8; 4] = b"TLPC";
+/// File extension of chunked collection files in storage.
+pub const EXTENSION: &str = "tlpc";
+pub const VERSION: u16 = 1;
+pub const HEADER_LEN: usize = 52;
+
+/// Items are packed into blocks of roughly this many uncompressed bytes;
+/// an item larger than this gets a block of its own.
+const BLOCK_TARGET: usize = 64 * 1024;
+
+/// Whether `data` starts like a chunked collection file.
+pub fn is_chunked(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+#[derive(Debug, Clone)]
+pub struct Header {
+    pub index_len: u32,
+    /// Length of the collection JSON, used as the collection's size.
+    pub json_len: u64,
+    pub content_hash: String,
+}
+
+impl Header {
+    pub fn parse(data: &[u8]) -> Result<Self, AppError> {
+        if data.len() < HEADER_LEN || !is_chunked(data) {
+            return Err(corrupt("truncated header"));
+        }
+        let version = u16::from_le_bytes([data[4], data[5]]);
+        if version != VERSION {
+            return Err(corrupt(&format!("unsupported version {}", version)));
+        }
+        Ok(Self {
+            index_len: u32::from_le_bytes(data[8..12].try_into().unwrap()),
+            json_len: u64::from_le_bytes(data[12..20].try_into().unwrap()),
+            content_hash: hex::encode(&data[20..52]),
+        })
+    }
+
+    fn data_start(&self) -> u64 {
+        (HEADER_LEN + self.index_len as usize) as u64
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BlockEntry {
+    offset: u64,
+    len: u32,
+}
+
+/// Location of one item: its block and its byte range within the
+/// decompressed block.
+#[derive(Debug, Serialize, Deserialize)]
+struct ItemEntry {
+    id: String,
+    block: u32,
+    start: u32,
+    len: u32,
+}
+
+/// Collection fields other than the items, plus where each item is stored.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChunkIndex {
+    pub id: String,
+    pub name: String,
+    blocks: Vec<BlockEntry>,
+    items: Vec<ItemEntry>,
+    #[serde(skip)]
+    positions: HashMap<String, usize>,
+}
+
+impl ChunkIndex {
+    fn decode(compressed: &[u8]) -> Result<Self, AppError> {
+        let json = decompress_block(compressed)?;
+        let mut index: ChunkIndex = serde_json::from_slice(&json)?;
+        index.positions = HashMap::with_capacity(index.items.len());
+        for (position, item) in index.items.iter().enumerate() {
+            if item.block as usize >= index.blocks.len() {
+                return Err(corrupt(&format!("item {} points past the last block", item.id)));
+            }
+            if index.positions.insert(item.id.clone(), position).is_some() {
+                return Err(AppError::InvalidContent(format!(
+                    "Collection {} contains duplicate item id: {}",
+                    index.id, item.id
+                )));
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn position(&self, item_id: &str) -> Option<usize> {
+        self.positions.get(item_id).copied()
+    }
+
+    /// Approximate memory held by the index, for cache weighing.
+    pub fn weight(&self) -> u32 {
+        let ids: usize = self.items.iter().map(|item| item.id.len() * 2 + 64).sum();
+        (ids + self.blocks.len() * 16).try_into().unwrap_or(u32::MAX)
+    }
+}
+
+/// A collection encoded into the chunked format.
+pub struct Encoded {
+    pub bytes: Vec<u8>,
+    pub content_hash: String,
+    pub json_len: u64,
+}
+
+pub fn encode(collection: &ContentCollection) -> Result<Encoded, AppError> {
+    let json = serde_json::to_vec(collection)?;
+    let digest = Sha256::digest(&json);
+
+    let mut blocks = Vec::new();
+    let mut block_entries = Vec::new();
+    let mut items = Vec::with_capacity(collection.items.len());
+    let mut current = Vec::new();
+    let mut offset = 0u64;
+
+    let mut flush = |current: &mut Vec<u8>, blocks: &mut Vec<u8>, entries: &mut Vec<BlockEntry>| {
+        let compressed = lz4_flex::block::compress_prepend_size(current);
+        entries.push(BlockEntry {
+            offset,
+            len: compressed.len() as u32,
+        });
+        offset += compressed.len() as u64;
+        blocks.extend_from_slice(&compressed);
+        current.clear();
+    };
+
+    for item in &collection.items {
+        let item_json = serde_json::to_vec(item)?;
+        if !current.is_empty() && current.len() + item_json.len() > BLOCK_TARGET {
+            flush(&mut current, &mut blocks, &mut block_entries);
+        }
+        items.push(ItemEntry {
+            id: item.id.clone(),
+            block: block_entries.len() as u32,
+            start: current.len() as u32,
+            len: item_json.len() as u32,
+        });
+        current.extend_from_slice(&item_json);
+    }
+    if !current.is_empty() {
+        flush(&mut current, &mut blocks, &mut block_entries);
+    }
+
+    let index = ChunkIndex {
+        id: collection.id.clone(),
+        name: collection.name.clone(),
+        blocks: block_entries,
+        items,
+        positions: HashMap::new(),
+    };
+    let index = lz4_flex::block::compress_prepend_size(&serde_json::to_vec(&index)?);
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + index.len() + blocks.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&[0, 0]);
+    bytes.extend_from_slice(&(index.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(json.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&digest);
+    bytes.extend_from_slice(&index);
+    bytes.extend_from_slice(&blocks);
+
+    Ok(Encoded {
+        bytes,
+        content_hash: hex::encode(digest),
+        json_len: json.len() as u64,
+    })
+}
+
+/// Decodes a whole chunked file held in memory.
+pub fn decode(data: &[u8]) -> Result<(Header, ContentCollection), AppError> {
+    let header = Header::parse(data)?;
+    let data_start = header.data_start() as usize;
+    let index = ChunkIndex::decode(data.get(HEADER_LEN..data_start).ok_or_else(|| corrupt("truncated index"))?)?;
+
+    let mut blocks = Vec::with_capacity(index.blocks.len());
+    for block in &index.blocks {
+        let start = data_start + block.offset as usize;
+        let compressed = data
+            .get(start..start + block.len as usize)
+            .ok_or_else(|| corrupt("truncated block"))?;
+        blocks.push(decompress_block(compressed)?);
+    }
+
+    let items = index
+        .items
+        .iter()
+        .map(|entry| parse_item(&blocks[entry.block as usize], entry))
+        .collect::<Result<_, _>>()?;
+    let collection = ContentCollection {
+        id: index.id,
+        name: index.name,
+        items,
+    };
+    Ok((header, collection))
+}
+
+/// Reads the header of an open chunked file. Returns `None` for files in
+/// another format; the file position is unspecified afterwards.
+pub async fn read_header(file: &mut tokio::fs::File) -> Result<Option<Header>, AppError> {
+    let mut header = [0u8; HEADER_LEN];
+    file.seek(std::io::SeekFrom::Start(0)).await?;
+    match file.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    if !is_chunked(&header) {
+        return Ok(None);
+    }
+    Header::parse(&header).map(Some)
+}
+
+pub async fn read_index(file: &mut tokio::fs::File, header: &Header) -> Result<ChunkIndex, AppError> {
+    let mut compressed = vec![0u8; header.index_len as usize];
+    file.seek(std::io::SeekFrom::Start(HEADER_LEN as u64)).await?;
+    file.read_exact(&mut compressed).await?;
+    ChunkIndex::decode(&compressed)
+}
+
+/// Reads the items at `positions`, decompressing only the blocks that hold
+/// them.
+pub async fn read_items(
+    file: &mut tokio::fs::File,
+    header: &Header,
+    index: &ChunkIndex,
+    positions: Range<usize>,
+) -> Result<Vec<ContentItem>, AppError> {
+    let entries = &index.items[positions];
+    let mut items = Vec::with_capacity(entries.len());
+    let mut block: Option<(u32, Vec<u8>)> = None;
+
+    for entry in entries {
+        if block.as_ref().map(|(number, _)| *number) != Some(entry.block) {
+            let location = &index.blocks[entry.block as usize];
+            let mut compressed = vec![0u8; location.len as usize];
+            file.seek(std::io::SeekFrom::Start(header.data_start() + location.offset))
+                .await?;
+            file.read_exact(&mut compressed).await?;
+            block = Some((entry.block, decompress_block(&compressed)?));
+        }
+        let (_, data) = block.as_ref().unwrap();
+        items.push(parse_item(data, entry)?);
+    }
+    Ok(items)
+}
+
+fn parse_item(block: &[u8], entry: &ItemEntry) -> Result<ContentItem, AppError> {
+    let start = entry.start as usize;
+    let json = block
+        .get(start..start + entry.len as usize)
+        .ok_or_else(|| corrupt(&format!("item {} lies outside its block", entry.id)))?;
+    Ok(serde_json::from_slice(json)?)
+}
+
+fn decompress_block(data: &[u8]) -> Result<Vec<u8>, AppError> {
+    lz4_flex::block::decompress_size_prepended(data).map_err(|e| AppError::Lz4(e.to_string()))
+}
+
+fn corrupt(reason: &str) -> AppError {
+    AppError::InvalidContent(format!("Corrupt collection file: {}", reason))
+}
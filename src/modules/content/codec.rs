@@ -0,0 +1,26 @@
+use crate::error::AppError;
+use lz4_flex::frame::{FrameDecoder, FrameEncoder};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+
+/// Compresses a JSON payload into the LZ4 frame format used for collection
+/// files in storage.
+pub fn compress(json: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = FrameEncoder::new(Vec::with_capacity(json.len() / 2));
+    encoder.write_all(json)?;
+    encoder.finish().map_err(std::io::Error::other)
+}
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, AppError> {
+    let mut decoder = FrameDecoder::new(data);
+    let mut decompressed = Vec::new();
+    decoder
+        .read_to_end(&mut decompressed)
+        .map_err(|e| AppError::Lz4(e.to_string()))?;
+    Ok(decompressed)
+}
+
+/// Hex-encoded SHA-256 of a decompressed collection, used as its ETag.
+pub fn content_hash(json: &[u8]) -> String {
+    hex::encode(Sha256::digest(json))
+}
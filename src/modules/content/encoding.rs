@@ -0,0 +1,225 @@
+use axum::http::{header::ACCEPT_ENCODING, HeaderMap};
+use std::io::Write;
+
+use super::codec;
+use crate::config::Config;
+
+/// Buffer size of the Brotli encoders.
+const BROTLI_BUFFER: usize = 64 * 1024;
+
+/// Brotli quality and zstd level for payloads encoded as they stream. Each
+/// response pays for its own encode, so these trade some ratio for speed
+/// compared with the cached encodes of `encode`.
+const STREAM_BROTLI_QUALITY: u32 = 5;
+const STREAM_ZSTD_LEVEL: i32 = 3;
+
+/// Content codings the content module can serve pre-encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Identity,
+    Brotli,
+    Zstd,
+    Gzip,
+    Deflate,
+    /// An LZ4 frame of the JSON. Legacy storage files are streamed as-is,
+    /// chunked ones are re-encoded. Not a registered content coding, so only
+    /// sent to clients that ask for `lz4` by name.
+    Lz4,
+}
+
+/// Server preference when a client accepts several codings equally.
+pub const SUPPORTED: [Encoding; 5] = [
+    Encoding::Brotli,
+    Encoding::Zstd,
+    Encoding::Gzip,
+    Encoding::Deflate,
+    Encoding::Lz4,
+];
+
+/// The codings offered for collection payloads: the configured compression
+/// algorithms in server preference order, plus the stored LZ4 frame.
+pub fn offered(config: &Config) -> Vec<Encoding> {
+    SUPPORTED
+        .into_iter()
+        .filter(|encoding| *encoding == Encoding::Lz4 || config.compression.contains(encoding))
+        .collect()
+}
+
+impl Encoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Identity => "identity",
+            Encoding::Brotli => "br",
+            Encoding::Zstd => "zstd",
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+            Encoding::Lz4 => "lz4",
+        }
+    }
+
+    pub fn parse(token: &str) -> Option<Self> {
+        match token.trim().to_ascii_lowercase().as_str() {
+            "identity" => Some(Encoding::Identity),
+            "br" => Some(Encoding::Brotli),
+            "zstd" => Some(Encoding::Zstd),
+            "gzip" | "x-gzip" => Some(Encoding::Gzip),
+            "deflate" => Some(Encoding::Deflate),
+            "lz4" => Some(Encoding::Lz4),
+            _ => None,
+        }
+    }
+
+    /// ETag of a representation sent with this coding, derived from the ETag
+    /// of the uncompressed one. Each coding is a distinct representation and
+    /// needs its own strong validator.
+    pub fn etag(&self, etag: String) -> String {
+        match self {
+            Encoding::Identity => etag,
+            other => format!("{}-{}\"", etag.trim_end_matches('"'), other.as_str()),
+        }
+    }
+
+    /// Encodes a complete payload. `Identity` passes through unchanged.
+    pub fn encode(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Identity => Ok(data.to_vec()),
+            Encoding::Lz4 => codec::compress(data),
+            Encoding::Brotli => {
+                let mut output = Vec::new();
+                {
+                    let mut encoder = brotli::CompressorWriter::new(&mut output, BROTLI_BUFFER, 9, 22);
+                    encoder.write_all(data)?;
+                }
+                Ok(output)
+            }
+            Encoding::Zstd => zstd::encode_all(data, 9),
+            Encoding::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    /// An encoder that compresses a payload piece by piece, with cheaper
+    /// settings than `encode`. `None` for `Identity`.
+    pub fn stream_encoder(&self) -> std::io::Result<Option<StreamEncoder>> {
+        Ok(Some(match self {
+            Encoding::Identity => return Ok(None),
+            Encoding::Lz4 => StreamEncoder::Lz4(lz4_flex::frame::FrameEncoder::new(Vec::new())),
+            Encoding::Brotli => {
+                StreamEncoder::Brotli(Box::new(brotli::CompressorWriter::new(
+                    Vec::new(),
+                    BROTLI_BUFFER,
+                    STREAM_BROTLI_QUALITY,
+                    22,
+                )))
+            }
+            Encoding::Zstd => StreamEncoder::Zstd(zstd::stream::write::Encoder::new(Vec::new(), STREAM_ZSTD_LEVEL)?),
+            Encoding::Gzip => {
+                StreamEncoder::Gzip(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()))
+            }
+            Encoding::Deflate => {
+                StreamEncoder::Deflate(flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default()))
+            }
+        }))
+    }
+}
+
+/// Incremental encoder for a response body. Each write returns the
+/// compressed output produced so far, which may be empty.
+pub enum StreamEncoder {
+    Brotli(Box<brotli::CompressorWriter<Vec<u8>>>),
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+    Deflate(flate2::write::ZlibEncoder<Vec<u8>>),
+    Lz4(lz4_flex::frame::FrameEncoder<Vec<u8>>),
+}
+
+impl StreamEncoder {
+    pub fn write(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let output = match self {
+            StreamEncoder::Brotli(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+            StreamEncoder::Zstd(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+            StreamEncoder::Gzip(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+            StreamEncoder::Deflate(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+            StreamEncoder::Lz4(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+        };
+        Ok(std::mem::take(output))
+    }
+
+    /// Ends the stream and returns the remaining output.
+    pub fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            StreamEncoder::Brotli(encoder) => Ok((*encoder).into_inner()),
+            StreamEncoder::Zstd(encoder) => encoder.finish(),
+            StreamEncoder::Gzip(encoder) => encoder.finish(),
+            StreamEncoder::Deflate(encoder) => encoder.finish(),
+            StreamEncoder::Lz4(encoder) => encoder.finish().map_err(std::io::Error::other),
+        }
+    }
+}
+
+/// Picks the coding for a response from the request's `Accept-Encoding`,
+/// restricted to `offered`. Highest q-value wins; ties go to the order of
+/// `offered`. `*` matches any offered coding except `lz4`.
+pub fn negotiate(headers: &HeaderMap, offered: &[Encoding]) -> Encoding {
+    let Some(accept) = headers.get(ACCEPT_ENCODING).and_then(|v| v.to_str().ok()) else {
+        return Encoding::Identity;
+    };
+
+    let mut explicit: Vec<(Encoding, f32)> = Vec::new();
+    let mut wildcard: Option<f32> = None;
+    for part in accept.split(',') {
+        let mut pieces = part.split(';');
+        let token = pieces.next().unwrap_or_default().trim();
+        let quality = pieces
+            .filter_map(|p| p.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        if token == "*" {
+            wildcard = Some(quality);
+        } else if let Some(encoding) = Encoding::parse(token) {
+            explicit.push((encoding, quality));
+        }
+    }
+
+    let quality_of = |encoding: Encoding| {
+        explicit
+            .iter()
+            .find(|(e, _)| *e == encoding)
+            .map(|(_, q)| *q)
+            .or(if encoding == Encoding::Lz4 { None } else { wildcard })
+            .unwrap_or(0.0)
+    };
+
+    let mut best = (Encoding::Identity, 0.0);
+    for encoding in offered {
+        let quality = quality_of(*encoding);
+        if quality > best.1 {
+            best = (*encoding, quality);
+        }
+    }
+    best.0
+}
@@ -0,0 +1,108 @@
+use axum::{
+    http::{
+        header::{CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+
+use super::store::{CachedCollection, CollectionMetadata};
+use crate::config::Config;
+
+const PRIVATE_CACHE_CONTROL: &str = "private, no-cache";
+
+/// Validators and caching policy for one response representation.
+pub struct Validators {
+    pub etag: String,
+    pub last_modified: DateTime<Utc>,
+    pub cache_control: String,
+}
+
+impl Validators {
+    /// Validators for a representation of `entry` identified by `etag`.
+    pub fn for_collection(entry: &CachedCollection, etag: String, config: &Config) -> Self {
+        Self::for_metadata(&entry.meta, etag, config)
+    }
+
+    /// Validators for a representation of the collection described by
+    /// `meta`. Private collections are never stored by shared caches.
+    pub fn for_metadata(meta: &CollectionMetadata, etag: String, config: &Config) -> Self {
+        let cache_control = if meta.private {
+            PRIVATE_CACHE_CONTROL.to_string()
+        } else {
+            meta.cache_control
+                .clone()
+                .unwrap_or_else(|| config.default_cache_control.clone())
+        };
+
+        Self {
+            etag,
+            last_modified: meta.last_modified(),
+            cache_control,
+        }
+    }
+
+    /// Whether the client's copy is current. `If-None-Match` takes precedence
+    /// over `If-Modified-Since`, as in RFC 9110, and uses weak comparison.
+    pub fn is_not_modified(&self, request: &HeaderMap) -> bool {
+        if let Some(value) = request.get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+            let etag = opaque_tag(&self.etag);
+            return value
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || opaque_tag(tag) == etag);
+        }
+
+        request
+            .get(IF_MODIFIED_SINCE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+            .is_some_and(|since| self.last_modified.timestamp() <= since.timestamp())
+    }
+
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if let Ok(etag) = HeaderValue::from_str(&self.etag) {
+            headers.insert(ETAG, etag);
+        }
+        if let Ok(date) = HeaderValue::from_str(&http_date(self.last_modified)) {
+            headers.insert(LAST_MODIFIED, date);
+        }
+        if let Ok(cache_control) = HeaderValue::from_str(&self.cache_control) {
+            headers.insert(CACHE_CONTROL, cache_control);
+        }
+    }
+
+    /// Answers with `304 Not Modified` when the client's copy is current,
+    /// otherwise with `body`. Both carry the validators.
+    pub fn respond(&self, request: &HeaderMap, body: impl FnOnce() -> Response) -> Response {
+        self.not_modified(request).unwrap_or_else(|| {
+            let mut response = body();
+            self.apply(response.headers_mut());
+            response
+        })
+    }
+
+    /// The `304 Not Modified` response, if the client's copy is current.
+    pub fn not_modified(&self, request: &HeaderMap) -> Option<Response> {
+        self.is_not_modified(request).then(|| {
+            let mut response = StatusCode::NOT_MODIFIED.into_response();
+            self.apply(response.headers_mut());
+            response
+        })
+    }
+}
+
+/// A weak ETag for `etag`, for representations that the compression layer
+/// may send in more than one coding.
+pub fn weak(etag: String) -> String {
+    format!("W/{}", etag)
+}
+
+fn opaque_tag(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
+
+fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
@@ -0,0 +1,70 @@
+/// Language ranges from an `Accept-Language` value (or a `lang` parameter in
+/// the same syntax), lowercased, with their q-values.
+#[derive(Debug, Clone)]
+pub struct LanguagePreferences(Vec<(String, f32)>);
+
+impl LanguagePreferences {
+    pub fn parse(value: &str) -> Self {
+        let ranges = value
+            .split(',')
+            .filter_map(|part| {
+                let mut pieces = part.split(';');
+                let range = pieces.next()?.trim().to_ascii_lowercase();
+                if range.is_empty() {
+                    return None;
+                }
+                let quality = pieces
+                    .filter_map(|p| p.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((range, quality))
+            })
+            .collect();
+        Self(ranges)
+    }
+
+    /// How well a collection in language `tag` satisfies the preferences,
+    /// as `(quality, specificity)`. A range matches its own tag, more
+    /// specific tags (`pt` matches `pt-br`) and, as a fallback, its primary
+    /// language (`pt-br` matches `pt`). Untagged collections only match `*`.
+    fn score(&self, tag: Option<&str>) -> (f32, u8) {
+        let tag = tag.map(str::to_ascii_lowercase);
+        let mut best = (0.0, 0);
+        for (range, quality) in &self.0 {
+            let specificity = match tag.as_deref() {
+                _ if range == "*" => 1,
+                Some(tag) if tag == range => 4,
+                Some(tag) if tag.starts_with(range.as_str()) && tag[range.len()..].starts_with('-') => 3,
+                Some(tag) if range.starts_with(tag) && range[tag.len()..].starts_with('-') => 2,
+                _ => continue,
+            };
+            if (*quality, specificity) > best {
+                best = (*quality, specificity);
+            }
+        }
+        best
+    }
+
+    /// The candidate whose language fits best, if any is acceptable. Ties
+    /// go to the earlier candidate.
+    pub fn best<'a, T>(&self, candidates: &'a [T], language: impl Fn(&T) -> Option<&str>) -> Option<&'a T> {
+        let mut best: Option<(&T, (f32, u8))> = None;
+        for candidate in candidates {
+            let score = self.score(language(candidate));
+            if score.0 > 0.0 && best.is_none_or(|(_, current)| score > current) {
+                best = Some((candidate, score));
+            }
+        }
+        best.map(|(candidate, _)| candidate)
+    }
+
+    /// `candidates` ordered from best to worst fit; unacceptable ones last,
+    /// in their original order.
+    pub fn sort<T>(&self, candidates: &mut [T], language: impl Fn(&T) -> Option<&str>) {
+        candidates.sort_by(|a, b| {
+            self.score(language(b))
+                .partial_cmp(&self.score(language(a)))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+}
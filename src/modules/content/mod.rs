@@ -1,5 +1,15 @@
+pub mod chunked;
+pub mod codec;
+pub mod encoding;
+pub mod http_cache;
+pub mod language;
+pub mod query;
+pub mod reload;
 pub mod routes;
 pub mod store;
+pub mod streaming;
+pub mod warmup;
 
+pub use reload::Reloader;
 pub use routes::routes;
 pub use store::ContentStore;
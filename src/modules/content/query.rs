@@ -0,0 +1,338 @@
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+
+use super::store::{ContentItem, PageRequest};
+use crate::error::AppError;
+
+/// Item fields that can be selected with `fields` or left out with
+/// `exclude`, in the order they are serialized. Bit `i` of a `FieldSet`
+/// stands for `ITEM_FIELDS[i]`.
+pub const ITEM_FIELDS: [&str; 9] = [
+    "id",
+    "title",
+    "body",
+    "tags",
+    "authors",
+    "metadata",
+    "position",
+    "created_at",
+    "updated_at",
+];
+
+// Bits of the fields in `ITEM_FIELDS`.
+const ID: usize = 0;
+const TITLE: usize = 1;
+const BODY: usize = 2;
+const TAGS: usize = 3;
+const AUTHORS: usize = 4;
+const METADATA: usize = 5;
+const POSITION: usize = 6;
+const CREATED_AT: usize = 7;
+const UPDATED_AT: usize = 8;
+
+/// Item fields that can be sorted on.
+const SORT_FIELDS: [&str; 5] = ["id", "title", "position", "created_at", "updated_at"];
+
+#[derive(Debug, Clone)]
+enum Filter {
+    /// The item has this tag.
+    Tag(String),
+    /// One of the item's authors contains this text.
+    Author(String),
+    /// The item's metadata entry `key` equals `value`, or contains it when
+    /// the entry is an array.
+    Metadata { key: String, value: String },
+}
+
+#[derive(Debug, Clone)]
+struct SortKey {
+    field: &'static str,
+    descending: bool,
+}
+
+/// Query parameters of the item listing: paging, plus filters, sort order
+/// and a field selection applied to the cached collection, and the language
+/// as for the other reads.
+///
+/// `tag`, `author` and `metadata.<key>` may be repeated and must all match.
+/// `sort` is a comma-separated list of fields, each prefixed with `-` for
+/// descending order; items without a value sort last. Unknown parameters
+/// are rejected, except the cache buster `_`.
+#[derive(Debug, Default)]
+pub struct ItemQuery {
+    pub page: PageRequest,
+    pub lang: Option<String>,
+    filters: Vec<Filter>,
+    sort: Vec<SortKey>,
+    pub fields: FieldSet,
+}
+
+impl ItemQuery {
+    pub fn parse(params: Vec<(String, String)>) -> Result<Self, AppError> {
+        let mut query = ItemQuery::default();
+        let (mut fields, mut exclude) = (None, None);
+        for (name, value) in params {
+            match name.as_str() {
+                "limit" => query.page.limit = Some(parse_number(&name, &value)?),
+                "offset" => query.page.offset = Some(parse_number(&name, &value)?),
+                "cursor" => query.page.cursor = Some(value),
+                "tag" => query.filters.push(Filter::Tag(required(&name, value)?)),
+                "author" => query.filters.push(Filter::Author(required(&name, value)?.to_lowercase())),
+                "sort" => query.sort = parse_sort(&value)?,
+                "fields" => fields = Some(value),
+                "exclude" => exclude = Some(value),
+                "lang" => query.lang = Some(value),
+                "_" => {}
+                _ => match name.strip_prefix("metadata.") {
+                    Some("") => return Err(AppError::InvalidQuery("metadata filter needs a key".into())),
+                    Some(key) => query.filters.push(Filter::Metadata {
+                        key: key.to_string(),
+                        value: required(&name, value)?,
+                    }),
+                    None => return Err(AppError::InvalidQuery(format!("Unknown query parameter: {}", name))),
+                },
+            }
+        }
+        query.fields = FieldSet::parse(fields.as_deref(), exclude.as_deref())?;
+        Ok(query)
+    }
+
+    /// Whether the query filters or sorts, which needs every item of the
+    /// collection at hand.
+    pub fn selects(&self) -> bool {
+        !self.filters.is_empty() || !self.sort.is_empty()
+    }
+
+    /// The items matching every filter, in the requested order.
+    pub fn select<'a>(&self, items: &'a [ContentItem]) -> Vec<&'a ContentItem> {
+        let mut selected: Vec<_> = items
+            .iter()
+            .filter(|item| self.filters.iter().all(|filter| filter.matches(item)))
+            .collect();
+        if !self.sort.is_empty() {
+            selected.sort_by(|a, b| {
+                self.sort
+                    .iter()
+                    .map(|key| key.compare(a, b))
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+        selected
+    }
+}
+
+/// The item fields a response includes, from `fields` (only these) and
+/// `exclude` (all but these). Both default to every field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSet(u16);
+
+impl Default for FieldSet {
+    fn default() -> Self {
+        FieldSet::ALL
+    }
+}
+
+impl FieldSet {
+    pub const ALL: FieldSet = FieldSet((1 << ITEM_FIELDS.len()) - 1);
+
+    pub fn parse(fields: Option<&str>, exclude: Option<&str>) -> Result<Self, AppError> {
+        let mut set = match fields {
+            Some(fields) => FieldSet(parse_field_mask(fields)?),
+            None => FieldSet::ALL,
+        };
+        if let Some(exclude) = exclude {
+            set.0 &= !parse_field_mask(exclude)?;
+        }
+        Ok(set)
+    }
+
+    pub fn is_all(&self) -> bool {
+        *self == FieldSet::ALL
+    }
+
+    fn includes(&self, bit: usize) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+
+    /// `item` restricted to these fields, for serialization.
+    pub fn project<T: Borrow<ContentItem>>(self, item: T) -> Projected<T> {
+        Projected { item, fields: self }
+    }
+
+    /// ETag of a representation narrowed to these fields, derived from the
+    /// ETag of the full one.
+    pub fn etag(&self, etag: String) -> String {
+        if self.is_all() {
+            return etag;
+        }
+        let names: Vec<_> = ITEM_FIELDS
+            .into_iter()
+            .enumerate()
+            .filter_map(|(bit, name)| self.includes(bit).then_some(name))
+            .collect();
+        format!("{}-fields:{}\"", etag.trim_end_matches('"'), names.join("."))
+    }
+}
+
+/// An item serialized with only the fields of a `FieldSet`, in the same
+/// order and with the same omissions as `ContentItem` itself.
+pub struct Projected<T> {
+    item: T,
+    fields: FieldSet,
+}
+
+impl<T: Borrow<ContentItem>> Serialize for Projected<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let item = self.item.borrow();
+        if self.fields.is_all() {
+            return item.serialize(serializer);
+        }
+
+        // Destructured without `..`, so a new item field does not compile
+        // until it is projected here too.
+        let ContentItem {
+            id,
+            title,
+            body,
+            tags,
+            authors,
+            metadata,
+            position,
+            created_at,
+            updated_at,
+        } = item;
+        let fields = self.fields;
+        let mut map = serializer.serialize_map(None)?;
+        if fields.includes(ID) {
+            map.serialize_entry(ITEM_FIELDS[ID], id)?;
+        }
+        if fields.includes(TITLE) {
+            map.serialize_entry(ITEM_FIELDS[TITLE], title)?;
+        }
+        if fields.includes(BODY) {
+            map.serialize_entry(ITEM_FIELDS[BODY], body)?;
+        }
+        if fields.includes(TAGS) && !tags.is_empty() {
+            map.serialize_entry(ITEM_FIELDS[TAGS], tags)?;
+        }
+        if fields.includes(AUTHORS) && !authors.is_empty() {
+            map.serialize_entry(ITEM_FIELDS[AUTHORS], authors)?;
+        }
+        if fields.includes(METADATA) && !metadata.is_empty() {
+            map.serialize_entry(ITEM_FIELDS[METADATA], metadata)?;
+        }
+        if let Some(position) = position.filter(|_| fields.includes(POSITION)) {
+            map.serialize_entry(ITEM_FIELDS[POSITION], &position)?;
+        }
+        if let Some(created_at) = created_at.filter(|_| fields.includes(CREATED_AT)) {
+            map.serialize_entry(ITEM_FIELDS[CREATED_AT], &created_at)?;
+        }
+        if let Some(updated_at) = updated_at.filter(|_| fields.includes(UPDATED_AT)) {
+            map.serialize_entry(ITEM_FIELDS[UPDATED_AT], &updated_at)?;
+        }
+        map.end()
+    }
+}
+
+impl Filter {
+    fn matches(&self, item: &ContentItem) -> bool {
+        match self {
+            Filter::Tag(tag) => item.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            Filter::Author(author) => item.authors.iter().any(|a| a.to_lowercase().contains(author)),
+            Filter::Metadata { key, value } => match item.metadata.get(key) {
+                Some(Value::Array(values)) => values.iter().any(|v| scalar_eq(v, value)),
+                Some(v) => scalar_eq(v, value),
+                None => false,
+            },
+        }
+    }
+}
+
+fn scalar_eq(value: &Value, expected: &str) -> bool {
+    match value {
+        Value::String(s) => s.eq_ignore_ascii_case(expected),
+        Value::Number(_) | Value::Bool(_) => serde_json::from_str::<Value>(expected).is_ok_and(|parsed| parsed == *value),
+        _ => false,
+    }
+}
+
+impl SortKey {
+    fn compare(&self, a: &ContentItem, b: &ContentItem) -> Ordering {
+        let ordering = match self.field {
+            "id" => Some(a.id.cmp(&b.id)),
+            "title" => Some(a.title.to_lowercase().cmp(&b.title.to_lowercase())),
+            "position" => option_cmp(a.position, b.position),
+            "created_at" => option_cmp(a.created_at, b.created_at),
+            _ => option_cmp(a.updated_at, b.updated_at),
+        };
+        // `None` means one side has no value; those go last either way.
+        match ordering {
+            Some(ordering) if self.descending => ordering.reverse(),
+            Some(ordering) => ordering,
+            None => has_value(b, self.field).cmp(&has_value(a, self.field)),
+        }
+    }
+}
+
+fn has_value(item: &ContentItem, field: &str) -> bool {
+    match field {
+        "position" => item.position.is_some(),
+        "created_at" => item.created_at.is_some(),
+        "updated_at" => item.updated_at.is_some(),
+        _ => true,
+    }
+}
+
+fn option_cmp<T: Ord>(a: Option<T>, b: Option<T>) -> Option<Ordering> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        (None, None) => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn parse_sort(value: &str) -> Result<Vec<SortKey>, AppError> {
+    value
+        .split(',')
+        .map(|part| {
+            let part = part.trim();
+            let (descending, name) = match part.strip_prefix('-') {
+                Some(name) => (true, name),
+                None => (false, part),
+            };
+            let field = SORT_FIELDS
+                .into_iter()
+                .find(|field| *field == name)
+                .ok_or_else(|| AppError::InvalidQuery(format!("Cannot sort by: {}", part)))?;
+            Ok(SortKey { field, descending })
+        })
+        .collect()
+}
+
+fn parse_field_mask(value: &str) -> Result<u16, AppError> {
+    value.split(',').try_fold(0, |mask, field| {
+        let field = field.trim();
+        let bit = ITEM_FIELDS
+            .iter()
+            .position(|name| *name == field)
+            .ok_or_else(|| AppError::InvalidQuery(format!("Unknown field: {}", field)))?;
+        Ok(mask | 1 << bit)
+    })
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize, AppError> {
+    value
+        .parse()
+        .map_err(|_| AppError::InvalidQuery(format!("{} must be a non-negative integer", name)))
+}
+
+fn required(name: &str, value: String) -> Result<String, AppError> {
+    if value.trim().is_empty() {
+        Err(AppError::InvalidQuery(format!("{} must not be empty", name)))
+    } else {
+        Ok(value)
+    }
+}
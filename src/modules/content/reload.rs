@@ -0,0 +1,150 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use super::store::CollectionMetadata;
+use crate::error::AppError;
+use crate::AppState;
+
+/// File events arriving within this window are handled as one batch, so a
+/// write-then-rename or a burst of copies reloads each collection once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Keeps collections in sync with changes made outside the write API: a
+/// watcher on `storage_path` reloads collections whose files change, and a
+/// poll of the `collections` table drops cached copies of rows that changed
+/// or disappeared. Dropping the handle stops both.
+pub struct Reloader {
+    _watcher: Option<RecommendedWatcher>,
+    poller: Option<JoinHandle<()>>,
+}
+
+impl Drop for Reloader {
+    fn drop(&mut self) {
+        if let Some(poller) = &self.poller {
+            poller.abort();
+        }
+    }
+}
+
+impl Reloader {
+    pub fn spawn(state: Arc<AppState>) -> Result<Self, AppError> {
+        let watcher = if state.config.watch_storage {
+            Some(watch_storage(state.clone())?)
+        } else {
+            None
+        };
+
+        let poller = state
+            .config
+            .collections_poll_interval
+            .map(|interval| tokio::spawn(poll_collections(state, interval)));
+
+        Ok(Self {
+            _watcher: watcher,
+            poller,
+        })
+    }
+}
+
+fn watch_storage(state: Arc<AppState>) -> Result<RecommendedWatcher, AppError> {
+    std::fs::create_dir_all(&state.config.storage_path)?;
+    let root = state.config.storage_path.canonicalize()?;
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+        match result {
+            Ok(event) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+                    let _ = tx.send(event.paths);
+                }
+            }
+            Err(e) => warn!(error = %e, "Storage watcher error"),
+        }
+    })
+    .map_err(io_error)?;
+    watcher.watch(&root, RecursiveMode::Recursive).map_err(io_error)?;
+    info!(path = ?root, "Watching storage for changes");
+
+    tokio::spawn(handle_events(state, root, rx));
+    Ok(watcher)
+}
+
+async fn handle_events(state: Arc<AppState>, root: PathBuf, mut rx: mpsc::UnboundedReceiver<Vec<PathBuf>>) {
+    while let Some(paths) = rx.recv().await {
+        let mut changed: HashSet<PathBuf> = paths.into_iter().collect();
+        tokio::time::sleep(DEBOUNCE).await;
+        while let Ok(more) = rx.try_recv() {
+            changed.extend(more);
+        }
+
+        for path in changed {
+            let Some(relative) = relative_path(&root, &path) else {
+                continue;
+            };
+            match state.content_store.alias_for_file(&relative).await {
+                Ok(Some(alias)) => {
+                    debug!(alias, path = %relative, "Storage file changed");
+                    if let Err(e) = state.content_store.reload(&alias).await {
+                        warn!(alias, error = %e, "Failed to reload collection");
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!(path = %relative, error = %e, "Failed to resolve changed file"),
+            }
+        }
+    }
+}
+
+/// `path` relative to the storage root, as recorded in `collections.file_path`.
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Vec<_> = relative.iter().map(|part| part.to_str()).collect::<Option<_>>()?;
+    Some(parts.join("/"))
+}
+
+async fn poll_collections(state: Arc<AppState>, interval: Duration) {
+    let mut known: Option<HashMap<String, CollectionMetadata>> = None;
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        let current: HashMap<String, CollectionMetadata> = match state.content_store.list_collections().await {
+            Ok(rows) => rows.into_iter().map(|meta| (meta.alias.clone(), meta)).collect(),
+            Err(e) => {
+                warn!(error = %e, "Failed to poll collections");
+                continue;
+            }
+        };
+
+        if let Some(previous) = &known {
+            for (alias, meta) in &current {
+                match previous.get(alias) {
+                    None => info!(alias, "Discovered new collection"),
+                    // Writes through this server cache the entry for the
+                    // new row themselves.
+                    Some(old) if old != meta && !state.content_store.is_current(meta).await => {
+                        debug!(alias, "Collection row changed");
+                        state.content_store.invalidate(alias).await;
+                    }
+                    Some(_) => {}
+                }
+            }
+            for alias in previous.keys().filter(|alias| !current.contains_key(*alias)) {
+                info!(alias, "Collection removed");
+                state.content_store.invalidate(alias).await;
+            }
+        }
+        known = Some(current);
+    }
+}
+
+fn io_error(e: notify::Error) -> AppError {
+    AppError::Io(std::io::Error::other(e.to_string()))
+}
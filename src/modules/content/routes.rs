@@ -1,41 +1,485 @@
 use axum::{
-    extract::{Path, State},
+    body::Body,
+    extract::{Path, Query, State},
+    http::{
+        header::{ACCEPT_LANGUAGE, CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_TYPE, VARY},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
     routing::get,
     Json, Router,
 };
+use serde::Deserialize;
 use std::sync::Arc;
+use tokio_util::io::ReaderStream;
 
-use super::store::{CollectionMetadata, ContentCollection, ContentItem};
+use super::encoding::{self, Encoding};
+use super::http_cache::{self, Validators};
+use super::language::LanguagePreferences;
+use super::query::{FieldSet, ItemQuery};
+use super::streaming::Format;
+use super::store::{
+    CachedCollection, CollectionMetadata, CollectionUpsert, ContentItem, ItemPatch, ItemSource,
+    NewCollection, WriteMode,
+};
 use crate::error::AppError;
+use crate::middleware::NoCompression;
+use crate::modules::auth::{Principal, Scope};
 use crate::AppState;
 
 pub fn routes() -> Router<Arc<AppState>> {
     Router::new()
-        .route("/collections", get(list_collections))
-        .route("/collections/:alias", get(get_collection))
-        .route("/collections/:alias/items/:item_id", get(get_item))
+        .route("/collections", get(list_collections).post(create_collection))
+        .route(
+            "/collections/:alias",
+            get(get_collection)
+                .put(update_collection)
+                .delete(delete_collection),
+        )
+        .route("/collections/:alias/toc", get(get_toc))
+        .route("/collections/:alias/translations", get(list_translations))
+        .route("/collections/:alias/items", get(list_items).post(create_item))
+        .route(
+            "/collections/:alias/items/:item_id",
+            get(get_item).patch(update_item).delete(delete_item),
+        )
+}
+
+/// Loads a collection the caller is allowed to read.
+async fn readable_collection(
+    state: &AppState,
+    principal: &Principal,
+    alias: &str,
+) -> Result<Arc<CachedCollection>, AppError> {
+    principal.require(Scope::Read)?;
+    let entry = state.content_store.get_collection(alias).await?;
+    principal.authorize(Scope::Read, &entry.meta)?;
+    state.content_store.record_access(alias);
+    Ok(entry)
+}
+
+/// Opens the items of a collection the caller is allowed to read, without
+/// loading the whole collection when its storage format allows.
+async fn readable_items(state: &AppState, principal: &Principal, alias: &str) -> Result<ItemSource, AppError> {
+    principal.require(Scope::Read)?;
+    let source = state.content_store.item_source(alias).await?;
+    principal.authorize(Scope::Read, source.meta())?;
+    state.content_store.record_access(alias);
+    Ok(source)
+}
+
+/// Query parameters of a collection or item read: the language, in
+/// `Accept-Language` syntax (`tr`, `pt-BR,en;q=0.5`), and the item fields
+/// to include (`fields=id,title`) or leave out (`exclude=body`).
+#[derive(Debug, Default, Deserialize)]
+struct ReadQuery {
+    lang: Option<String>,
+    fields: Option<String>,
+    exclude: Option<String>,
+}
+
+impl ReadQuery {
+    fn fields(&self) -> Result<FieldSet, AppError> {
+        FieldSet::parse(self.fields.as_deref(), self.exclude.as_deref())
+    }
+}
+
+/// Query parameters of a table of contents read, which has no item fields
+/// to select: only the language, as for `ReadQuery`.
+#[derive(Debug, Default, Deserialize)]
+struct TocQuery {
+    lang: Option<String>,
+}
+
+/// The language a read asks for: its `lang` parameter, which must be
+/// matched, or else its `Accept-Language` header, which is only a
+/// preference and falls back to the collection that was asked for.
+#[derive(Clone, Copy)]
+struct LanguageRequest<'a> {
+    ranges: &'a str,
+    required: bool,
+}
+
+impl<'a> LanguageRequest<'a> {
+    fn from_request(lang: Option<&'a str>, headers: &'a HeaderMap) -> Option<Self> {
+        match lang {
+            Some(ranges) => Some(Self { ranges, required: true }),
+            None => headers
+                .get(ACCEPT_LANGUAGE)
+                .and_then(|v| v.to_str().ok())
+                .map(|ranges| Self { ranges, required: false }),
+        }
+    }
+}
+
+/// The readable collection in the translation group of `meta` whose
+/// language best matches `language`. `meta` itself wins ties, and is the
+/// answer when nothing matches a language that is only preferred.
+async fn translation(
+    state: &AppState,
+    principal: &Principal,
+    meta: &CollectionMetadata,
+    language: LanguageRequest<'_>,
+) -> Result<CollectionMetadata, AppError> {
+    if meta.translation_group.is_none() && !language.required {
+        return Ok(meta.clone());
+    }
+    let mut members: Vec<_> = state
+        .content_store
+        .translations(meta)
+        .await?
+        .into_iter()
+        .filter(|member| principal.can_read(member))
+        .collect();
+    members.sort_by_key(|member| member.alias != meta.alias);
+    match LanguagePreferences::parse(language.ranges).best(&members, |member| member.language.as_deref()) {
+        Some(best) => Ok(best.clone()),
+        None if !language.required => Ok(meta.clone()),
+        None => Err(AppError::NotFound(format!(
+            "No translation of {} matches language: {}",
+            meta.alias, language.ranges
+        ))),
+    }
+}
+
+/// Alias of the collection to serve for a read of `alias`: its translation
+/// matching the requested language, if any, otherwise `alias` itself. Uses
+/// the cached entry's metadata when there is one, so that reads sending
+/// `Accept-Language` stay off the database.
+async fn resolve_language(
+    state: &AppState,
+    principal: &Principal,
+    alias: String,
+    language: Option<LanguageRequest<'_>>,
+) -> Result<String, AppError> {
+    let Some(language) = language else {
+        return Ok(alias);
+    };
+    principal.require(Scope::Read)?;
+    let meta = match state.content_store.cached_metadata(&alias).await {
+        Some(meta) => meta,
+        None => state.content_store.get_metadata(&alias).await?,
+    };
+    principal.authorize(Scope::Read, &meta)?;
+    Ok(translation(state, principal, &meta, language).await?.alias)
+}
+
+/// Labels a read with the language served. The translation served may
+/// depend on `Accept-Language`, so caches must key on it.
+fn set_content_language(response: &mut Response, meta: &CollectionMetadata) {
+    if let Some(language) = meta.language.as_deref().and_then(|l| HeaderValue::from_str(l).ok()) {
+        response.headers_mut().insert(CONTENT_LANGUAGE, language);
+    }
+    response
+        .headers_mut()
+        .append(VARY, HeaderValue::from_static("accept-language"));
+}
+
+/// Checks that the caller may apply a `scope` write to an existing collection.
+async fn authorize_write(
+    state: &AppState,
+    principal: &Principal,
+    scope: Scope,
+    alias: &str,
+) -> Result<(), AppError> {
+    principal.require(scope)?;
+    let meta = state.content_store.get_metadata(alias).await?;
+    principal.authorize(scope, &meta)
 }
 
 async fn list_collections(
+    principal: Principal,
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<Vec<CollectionMetadata>>, AppError> {
-    let collections = state.content_store.list_collections().await?;
+    principal.require(Scope::Read)?;
+    let collections = state
+        .content_store
+        .list_collections()
+        .await?
+        .into_iter()
+        .filter(|meta| principal.can_read(meta))
+        .collect();
     Ok(Json(collections))
 }
 
 async fn get_collection(
+    principal: Principal,
     State(state): State<Arc<AppState>>,
     Path(alias): Path<String>,
-) -> Result<Json<Arc<ContentCollection>>, AppError> {
+    Query(read): Query<ReadQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
     tracing::info!("Hit get_collection for alias: {}", alias);
-    let collection = state.content_store.get_collection(&alias).await?;
-    Ok(Json(collection))
+    let fields = read.fields()?;
+    let language = LanguageRequest::from_request(read.lang.as_deref(), &headers);
+    let alias = resolve_language(&state, &principal, alias, language).await?;
+    let entry = readable_collection(&state, &principal, &alias).await?;
+    let format = Format::negotiate(&headers);
+    let encoding = encoding::negotiate(&headers, &encoding::offered(&state.config));
+    let etag = match format {
+        Format::Json => entry.etag(),
+        Format::Ndjson => entry.ndjson_etag(),
+    };
+    let etag = encoding.etag(fields.etag(etag));
+    let validators = Validators::for_collection(&entry, etag, &state.config);
+
+    let mut response = match validators.not_modified(&headers) {
+        Some(not_modified) => not_modified,
+        None => {
+            // Whole JSON is encoded once per coding and cached, or sent
+            // straight from a legacy LZ4 file. Large collections, NDJSON and
+            // field selections are compressed as they stream instead.
+            let whole = format == Format::Json && fields.is_all() && encoding != Encoding::Identity;
+            let frame = if whole { stored_frame(&state, &entry, encoding).await? } else { None };
+            let mut response = match frame {
+                Some((file, len)) => {
+                    encoded_response(Body::from_stream(ReaderStream::new(file)), format, Some(len), encoding)
+                }
+                None if whole && entry.size < state.config.stream_encoding_min_size => {
+                    let bytes = state.content_store.encoded_collection(&entry, encoding).await?;
+                    let len = bytes.len() as u64;
+                    encoded_response(Body::from(bytes), format, Some(len), encoding)
+                }
+                None => {
+                    let body = format.body(entry.collection.clone(), fields, encoding)?;
+                    encoded_response(body, format, None, encoding)
+                }
+            };
+            validators.apply(response.headers_mut());
+            response
+        }
+    };
+    // The coding is part of the representation and its ETag; never let the
+    // compression layer re-encode it.
+    response.extensions_mut().insert(NoCompression);
+    set_content_language(&mut response, &entry.meta);
+    response
+        .headers_mut()
+        .insert(VARY, HeaderValue::from_static("accept, accept-encoding, accept-language"));
+    Ok(response)
+}
+
+/// The stored file, when `encoding` is `lz4` and the file is a single LZ4
+/// frame that can be sent as-is.
+async fn stored_frame(
+    state: &AppState,
+    entry: &CachedCollection,
+    encoding: Encoding,
+) -> Result<Option<(tokio::fs::File, u64)>, AppError> {
+    if encoding != Encoding::Lz4 {
+        return Ok(None);
+    }
+    state.content_store.open_stored(&entry.meta).await
+}
+
+/// A collection response in `encoding`, with a `Content-Length` unless
+/// the body is streamed.
+fn encoded_response(body: Body, format: Format, len: Option<u64>, encoding: Encoding) -> Response {
+    let mut response = ([(CONTENT_TYPE, HeaderValue::from_static(format.content_type()))], body).into_response();
+    if encoding != Encoding::Identity {
+        response
+            .headers_mut()
+            .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.as_str()));
+    }
+    if let Some(len) = len {
+        response.headers_mut().insert(CONTENT_LENGTH, HeaderValue::from(len));
+    }
+    response
+}
+
+async fn create_collection(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Json(new): Json<NewCollection>,
+) -> Result<(StatusCode, Json<CollectionMetadata>), AppError> {
+    principal.authorize_alias(Scope::Write, &new.alias, new.collection.private)?;
+    let meta = state
+        .content_store
+        .save_collection(&new.alias, new.collection, WriteMode::Create)
+        .await?;
+    Ok((StatusCode::CREATED, Json(meta)))
+}
+
+async fn update_collection(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Path(alias): Path<String>,
+    Json(upsert): Json<CollectionUpsert>,
+) -> Result<Json<CollectionMetadata>, AppError> {
+    authorize_write(&state, &principal, Scope::Write, &alias).await?;
+    let meta = state
+        .content_store
+        .save_collection(&alias, upsert, WriteMode::Replace)
+        .await?;
+    Ok(Json(meta))
+}
+
+async fn delete_collection(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Path(alias): Path<String>,
+) -> Result<StatusCode, AppError> {
+    authorize_write(&state, &principal, Scope::Admin, &alias).await?;
+    state.content_store.delete_collection(&alias).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_items(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Path(alias): Path<String>,
+    Query(params): Query<Vec<(String, String)>>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let query = ItemQuery::parse(params)?;
+    let language = LanguageRequest::from_request(query.lang.as_deref(), &headers);
+    let alias = resolve_language(&state, &principal, alias, language).await?;
+    let (page, meta) = if query.selects() {
+        let entry = readable_collection(&state, &principal, &alias).await?;
+        (entry.query(&query)?, entry.meta.clone())
+    } else {
+        let mut source = readable_items(&state, &principal, &alias).await?;
+        let meta = source.meta().clone();
+        (source.page(&query.page).await?, meta)
+    };
+    let mut response = Json(page.map(|item| query.fields.project(item))).into_response();
+    set_content_language(&mut response, &meta);
+    Ok(response)
 }
 
 async fn get_item(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Path((alias, item_id)): Path<(String, String)>,
+    Query(read): Query<ReadQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let fields = read.fields()?;
+    let mut source = readable_items(&state, &principal, &alias).await?;
+    let mut position = source.item_position(&item_id)?;
+
+    // Translations are aligned by item id, or by position when the item has
+    // no counterpart with the same id but both collections are marked as
+    // aligned by position and have as many items.
+    if let Some(language) = LanguageRequest::from_request(read.lang.as_deref(), &headers) {
+        let target = translation(&state, &principal, source.meta(), language).await?;
+        if target.alias != source.meta().alias {
+            let aligned = readable_items(&state, &principal, &target.alias).await?;
+            let aligned_position = match aligned.item_position(&item_id) {
+                Ok(aligned_position) => Some(aligned_position),
+                Err(_) if parallel(source.meta(), aligned.meta()) && aligned.total() == source.total() => {
+                    Some(position)
+                }
+                // A preferred language falls back to the item asked for.
+                Err(_) if !language.required => None,
+                Err(e) => return Err(e),
+            };
+            if let Some(aligned_position) = aligned_position {
+                source = aligned;
+                position = aligned_position;
+            }
+        }
+    }
+
+    // Compressed by the layer in whatever coding the client accepts, so the
+    // ETag cannot be strong.
+    let etag = http_cache::weak(fields.etag(source.item_etag(position)));
+    let validators = Validators::for_metadata(source.meta(), etag, &state.config);
+    let mut response = match validators.not_modified(&headers) {
+        Some(not_modified) => not_modified,
+        None => {
+            let item = source.item(position).await?;
+            validators.respond(&headers, || Json(fields.project(item)).into_response())
+        }
+    };
+    set_content_language(&mut response, source.meta());
+    Ok(response)
+}
+
+/// Whether items of `a` and `b` correspond by position, as both collections
+/// declare.
+fn parallel(a: &CollectionMetadata, b: &CollectionMetadata) -> bool {
+    a.aligned_by_position && b.aligned_by_position
+}
+
+/// Ids, titles and body lengths of the items of a collection, for clients
+/// that render an index without downloading every body.
+async fn get_toc(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Path(alias): Path<String>,
+    Query(toc): Query<TocQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let language = LanguageRequest::from_request(toc.lang.as_deref(), &headers);
+    let alias = resolve_language(&state, &principal, alias, language).await?;
+    let entry = readable_collection(&state, &principal, &alias).await?;
+    let validators = Validators::for_collection(&entry, http_cache::weak(entry.toc_etag()), &state.config);
+    let mut response = validators.respond(&headers, || {
+        ([(CONTENT_TYPE, HeaderValue::from_static("application/json"))], entry.toc()).into_response()
+    });
+    set_content_language(&mut response, &entry.meta);
+    Ok(response)
+}
+
+/// The collections in the translation group of `alias`, best match for the
+/// request's `Accept-Language` first.
+async fn list_translations(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Path(alias): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    principal.require(Scope::Read)?;
+    let meta = state.content_store.get_metadata(&alias).await?;
+    principal.authorize(Scope::Read, &meta)?;
+
+    let mut members: Vec<_> = state
+        .content_store
+        .translations(&meta)
+        .await?
+        .into_iter()
+        .filter(|member| principal.can_read(member))
+        .collect();
+    if let Some(accept) = headers.get(ACCEPT_LANGUAGE).and_then(|v| v.to_str().ok()) {
+        LanguagePreferences::parse(accept).sort(&mut members, |member| member.language.as_deref());
+    }
+
+    let mut response = Json(members).into_response();
+    response
+        .headers_mut()
+        .insert(VARY, HeaderValue::from_static("accept-language"));
+    Ok(response)
+}
+
+async fn create_item(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Path(alias): Path<String>,
+    Json(item): Json<ContentItem>,
+) -> Result<(StatusCode, Json<ContentItem>), AppError> {
+    authorize_write(&state, &principal, Scope::Write, &alias).await?;
+    let item = state.content_store.create_item(&alias, item).await?;
+    Ok((StatusCode::CREATED, Json(item)))
+}
+
+async fn update_item(
+    principal: Principal,
     State(state): State<Arc<AppState>>,
     Path((alias, item_id)): Path<(String, String)>,
+    Json(patch): Json<ItemPatch>,
 ) -> Result<Json<ContentItem>, AppError> {
-    let item = state.content_store.get_item(&alias, &item_id).await?;
+    authorize_write(&state, &principal, Scope::Write, &alias).await?;
+    let item = state.content_store.update_item(&alias, &item_id, patch).await?;
     Ok(Json(item))
 }
+
+async fn delete_item(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Path((alias, item_id)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    authorize_write(&state, &principal, Scope::Write, &alias).await?;
+    state.content_store.delete_item(&alias, &item_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
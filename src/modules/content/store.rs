@@ -1,19 +1,48 @@
+use super::chunked::{self, ChunkIndex};
+use super::codec;
+use super::encoding::Encoding;
+use super::query::ItemQuery;
+use crate::config::Config;
 use crate::db::DbPool;
 use crate::error::AppError;
-use lz4_flex::frame::FrameDecoder;
+use crate::modules::metrics::Metrics;
+use crate::modules::search::SearchIndex;
+use axum::body::Bytes;
+use axum::http::HeaderValue;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
 use moka::future::Cache;
 use serde::{Deserialize, Serialize};
-use std::io::Read;
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::time::Duration;
-use tracing::{debug, info, instrument};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Mutex;
+use tracing::{debug, info, instrument, warn};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// One item of a collection. Only `id`, `title` and `body` are required;
+/// the structured fields are left out of the JSON when unset, so items
+/// without them serialize exactly as before they existed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ContentItem {
     pub id: String,
     pub title: String,
     pub body: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// Free-form fields that have no dedicated place in the schema.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+    /// Explicit sort order, independent of where the item is stored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,46 +52,709 @@ pub struct ContentCollection {
     pub items: Vec<ContentItem>,
 }
 
-#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+const SELECT_METADATA: &str = "SELECT id, alias, name, file_path, language, revision, private, \
+     content_hash, cache_control, preload, translation_group, aligned_by_position, created_at, updated_at FROM collections";
+
+pub const DEFAULT_PAGE_LIMIT: usize = 20;
+pub const MAX_PAGE_LIMIT: usize = 100;
+
+/// Page selection for item listings. `cursor` is the opaque `next_cursor`
+/// returned by a previous page and cannot be combined with `offset`.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageRequest {
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemPage<T = ContentItem> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
 pub struct CollectionMetadata {
     pub id: i64,
     pub alias: String,
     pub name: String,
     pub file_path: String,
     pub language: Option<String>,
+    pub revision: i64,
+    pub private: bool,
+    /// SHA-256 of the decompressed collection JSON, recorded on write.
+    pub content_hash: Option<String>,
+    pub cache_control: Option<String>,
+    /// Loaded into the cache at startup in the `preload` warm-up mode.
+    pub preload: bool,
+    /// Collections sharing a group are translations of the same work.
+    pub translation_group: Option<String>,
+    /// Items line up by position with those of the other translations that
+    /// set this flag, whatever their ids.
+    pub aligned_by_position: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
+impl CollectionMetadata {
+    pub fn last_modified(&self) -> DateTime<Utc> {
+        self.updated_at.unwrap_or(self.created_at)
+    }
+}
+
+/// A loaded collection together with the metadata row it was loaded for.
+#[derive(Debug)]
+pub struct CachedCollection {
+    pub meta: CollectionMetadata,
+    pub collection: Arc<ContentCollection>,
+    /// Content hash of the collection; taken from `meta.content_hash`, or
+    /// computed at load time for rows written before hashes were recorded.
+    pub content_hash: String,
+    /// Size of the decompressed JSON in bytes.
+    pub size: u64,
+    pub loaded_at: Instant,
+    /// Position of each item in `collection.items`, keyed by item id.
+    index: HashMap<String, usize>,
+    /// Serialized table of contents, built on first request.
+    toc: OnceLock<Bytes>,
+}
+
+/// Lightweight overview of a collection: its items without their bodies.
+#[derive(Debug, Serialize)]
+struct TableOfContents<'a> {
+    id: &'a str,
+    name: &'a str,
+    items: Vec<TocEntry<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct TocEntry<'a> {
+    id: &'a str,
+    title: &'a str,
+    /// Length of the body in characters.
+    body_length: usize,
+}
+
+/// Body of a collection write. `name` and `language` go to the `collections`
+/// row, `content` is what gets compressed into storage.
+#[derive(Debug, Deserialize)]
+pub struct CollectionUpsert {
+    pub name: String,
+    pub language: Option<String>,
+    #[serde(default)]
+    pub private: bool,
+    /// `Cache-Control` value for this collection's responses; the server
+    /// default applies when unset.
+    #[serde(default)]
+    pub cache_control: Option<String>,
+    /// Load the collection into the cache at startup.
+    #[serde(default)]
+    pub preload: bool,
+    /// Links the collection to its translations.
+    #[serde(default)]
+    pub translation_group: Option<String>,
+    /// Align items with translations by position when ids differ.
+    #[serde(default)]
+    pub aligned_by_position: bool,
+    pub content: ContentCollection,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewCollection {
+    pub alias: String,
+    #[serde(flatten)]
+    pub collection: CollectionUpsert,
+}
+
+/// Partial update of a single item; absent fields are left unchanged and
+/// present ones replace the current value.
+#[derive(Debug, Default, Deserialize)]
+pub struct ItemPatch {
+    pub title: Option<String>,
+    pub body: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub authors: Option<Vec<String>>,
+    pub metadata: Option<BTreeMap<String, serde_json::Value>>,
+    /// `Some(None)` when the patch sets `position` to `null`, clearing it.
+    #[serde(default, deserialize_with = "present")]
+    pub position: Option<Option<i64>>,
+}
+
+/// Deserializes a field that is present, `null` included, as `Some`, so a
+/// patch can tell a cleared field from an absent one.
+fn present<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteMode {
+    Create,
+    Replace,
+    Upsert,
+}
+
+/// Values written to the `collections` row alongside a storage file.
+struct RowValues<'a> {
+    name: &'a str,
+    language: Option<&'a str>,
+    private: bool,
+    cache_control: Option<&'a str>,
+    preload: bool,
+    translation_group: Option<&'a str>,
+    aligned_by_position: bool,
+}
+
+/// Maps item ids to their positions. Fails with the first id that occurs
+/// more than once.
+pub fn index_items(items: &[ContentItem]) -> Result<HashMap<String, usize>, String> {
+    let mut index = HashMap::with_capacity(items.len());
+    for (position, item) in items.iter().enumerate() {
+        if index.insert(item.id.clone(), position).is_some() {
+            return Err(item.id.clone());
+        }
+    }
+    Ok(index)
+}
+
+/// Checks the items of a collection before it is written: ids must be
+/// unique, and the structured fields well-formed.
+pub fn validate_collection(collection: &ContentCollection) -> Result<(), String> {
+    index_items(&collection.items).map_err(|id| format!("Duplicate item id: {}", id))?;
+    collection.items.iter().try_for_each(validate_item)
+}
+
+pub fn validate_item(item: &ContentItem) -> Result<(), String> {
+    if item.id.is_empty() {
+        return Err("Item id must not be empty".into());
+    }
+    for (field, values) in [("tags", &item.tags), ("authors", &item.authors)] {
+        let mut seen = HashSet::with_capacity(values.len());
+        for value in values {
+            if value.trim().is_empty() {
+                return Err(format!("Item {}: {} must not contain empty values", item.id, field));
+            }
+            if !seen.insert(value.as_str()) {
+                return Err(format!("Item {}: duplicate value in {}: {}", item.id, field, value));
+            }
+        }
+    }
+    if item.metadata.keys().any(|key| key.trim().is_empty()) {
+        return Err(format!("Item {}: metadata keys must not be empty", item.id));
+    }
+    if let (Some(created), Some(updated)) = (item.created_at, item.updated_at) {
+        if updated < created {
+            return Err(format!("Item {}: updated_at is before created_at", item.id));
+        }
+    }
+    Ok(())
+}
+
+/// Resolves `page` against a listing of `total` items, returning the
+/// selected positions and the effective limit. `position` finds the item a
+/// cursor names. Errors are query errors, like those of `ItemQuery::parse`.
+fn page_range(
+    page: &PageRequest,
+    total: usize,
+    position: impl Fn(&str) -> Option<usize>,
+) -> Result<(Range<usize>, usize), AppError> {
+    let limit = page.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+    if limit == 0 || limit > MAX_PAGE_LIMIT {
+        return Err(AppError::InvalidQuery(format!(
+            "limit must be between 1 and {}",
+            MAX_PAGE_LIMIT
+        )));
+    }
+
+    let offset = match (&page.cursor, page.offset) {
+        (Some(_), Some(_)) => {
+            return Err(AppError::InvalidQuery(
+                "cursor and offset cannot be combined".into(),
+            ))
+        }
+        (Some(cursor), None) => {
+            position(cursor).ok_or_else(|| AppError::InvalidQuery(format!("Invalid cursor: {}", cursor)))? + 1
+        }
+        (None, offset) => offset.unwrap_or(0),
+    };
+
+    let start = offset.min(total);
+    let end = start.saturating_add(limit).min(total);
+    Ok((start..end, limit))
+}
+
+impl<T> ItemPage<T> {
+    /// The same page with each item converted by `f`.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> ItemPage<U> {
+        ItemPage {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            offset: self.offset,
+            limit: self.limit,
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+impl ItemPage {
+    fn new(items: Vec<ContentItem>, total: usize, range: Range<usize>, limit: usize) -> Self {
+        let next_cursor = if range.end < total {
+            items.last().map(|item| item.id.clone())
+        } else {
+            None
+        };
+        Self {
+            items,
+            total,
+            offset: range.start,
+            limit,
+            next_cursor,
+        }
+    }
+}
+
+impl CachedCollection {
+    /// Wraps a collection for caching, indexing its items by id.
+    fn new(
+        meta: CollectionMetadata,
+        collection: ContentCollection,
+        content_hash: String,
+        size: u64,
+    ) -> Result<Self, AppError> {
+        let index = index_items(&collection.items).map_err(|id| {
+            AppError::InvalidContent(format!(
+                "Collection {} contains duplicate item id: {}",
+                meta.alias, id
+            ))
+        })?;
+        Ok(Self {
+            meta,
+            collection: Arc::new(collection),
+            content_hash,
+            size,
+            loaded_at: Instant::now(),
+            index,
+            toc: OnceLock::new(),
+        })
+    }
+
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.content_hash)
+    }
+
+    /// ETag of the table of contents.
+    pub fn toc_etag(&self) -> String {
+        format!("\"{}-toc\"", self.content_hash)
+    }
+
+    /// The table of contents as JSON, computed once per cached collection.
+    pub fn toc(&self) -> Bytes {
+        self.toc
+            .get_or_init(|| {
+                let collection = &self.collection;
+                let toc = TableOfContents {
+                    id: &collection.id,
+                    name: &collection.name,
+                    items: collection
+                        .items
+                        .iter()
+                        .map(|item| TocEntry {
+                            id: &item.id,
+                            title: &item.title,
+                            body_length: item.body.chars().count(),
+                        })
+                        .collect(),
+                };
+                Bytes::from(serde_json::to_vec(&toc).expect("serializing a table of contents cannot fail"))
+            })
+            .clone()
+    }
+
+    /// ETag of the collection served as NDJSON.
+    pub fn ndjson_etag(&self) -> String {
+        format!("\"{}-ndjson\"", self.content_hash)
+    }
+
+    /// ETag of the collection served with a content coding; each coding is a
+    /// distinct representation and needs its own strong validator.
+    pub fn encoded_etag(&self, encoding: Encoding) -> String {
+        encoding.etag(self.etag())
+    }
+
+    pub fn page(&self, page: &PageRequest) -> Result<ItemPage, AppError> {
+        let items = &self.collection.items;
+        let (range, limit) = page_range(page, items.len(), |cursor| self.index.get(cursor).copied())?;
+        Ok(ItemPage::new(items[range.clone()].to_vec(), items.len(), range, limit))
+    }
+
+    /// A page of the items selected by `query`. `total` counts the
+    /// matching items, and cursors refer to positions among them.
+    pub fn query(&self, query: &ItemQuery) -> Result<ItemPage, AppError> {
+        let selected = query.select(&self.collection.items);
+        let (range, limit) = page_range(&query.page, selected.len(), |cursor| {
+            selected.iter().position(|item| item.id == cursor)
+        })?;
+        let items = selected[range.clone()].iter().map(|item| (*item).clone()).collect();
+        Ok(ItemPage::new(items, selected.len(), range, limit))
+    }
+
+    pub fn item(&self, item_id: &str) -> Result<&ContentItem, AppError> {
+        self.item_position(item_id).map(|index| &self.collection.items[index])
+    }
+
+    pub fn item_position(&self, item_id: &str) -> Result<usize, AppError> {
+        self.index
+            .get(item_id)
+            .copied()
+            .ok_or_else(|| AppError::NotFound(format!("Item {} not found in {}", item_id, self.meta.alias)))
+    }
+}
+
+/// A chunked collection file read item by item, for reads of a collection
+/// that is not cached. Holds the file open so every read sees the content
+/// the index was taken from, even if the file is replaced meanwhile.
+#[derive(Debug)]
+pub struct StoredCollection {
+    pub meta: CollectionMetadata,
+    pub content_hash: String,
+    file: tokio::fs::File,
+    header: chunked::Header,
+    index: Arc<ChunkIndex>,
+}
+
+/// Where item reads are served from: the cached collection when there is
+/// one, otherwise the stored file if its format allows reading items on
+/// their own.
+#[derive(Debug)]
+pub enum ItemSource {
+    Cached(Arc<CachedCollection>),
+    Stored(Box<StoredCollection>),
+}
+
+impl ItemSource {
+    pub fn meta(&self) -> &CollectionMetadata {
+        match self {
+            ItemSource::Cached(entry) => &entry.meta,
+            ItemSource::Stored(stored) => &stored.meta,
+        }
+    }
+
+    /// ETag of a single item's representation. The item's position within
+    /// this exact collection content identifies it uniquely.
+    pub fn item_etag(&self, position: usize) -> String {
+        let content_hash = match self {
+            ItemSource::Cached(entry) => &entry.content_hash,
+            ItemSource::Stored(stored) => &stored.content_hash,
+        };
+        format!("\"{}-{}\"", content_hash, position)
+    }
+
+    /// Number of items in the collection.
+    pub fn total(&self) -> usize {
+        match self {
+            ItemSource::Cached(entry) => entry.collection.items.len(),
+            ItemSource::Stored(stored) => stored.index.len(),
+        }
+    }
+
+    pub fn item_position(&self, item_id: &str) -> Result<usize, AppError> {
+        match self {
+            ItemSource::Cached(entry) => entry.item_position(item_id),
+            ItemSource::Stored(stored) => stored.index.position(item_id).ok_or_else(|| {
+                AppError::NotFound(format!("Item {} not found in {}", item_id, stored.meta.alias))
+            }),
+        }
+    }
+
+    pub async fn item(&mut self, position: usize) -> Result<ContentItem, AppError> {
+        match self {
+            ItemSource::Cached(entry) => Ok(entry.collection.items[position].clone()),
+            ItemSource::Stored(stored) => {
+                let mut items =
+                    chunked::read_items(&mut stored.file, &stored.header, &stored.index, position..position + 1)
+                        .await?;
+                Ok(items.remove(0))
+            }
+        }
+    }
+
+    pub async fn page(&mut self, page: &PageRequest) -> Result<ItemPage, AppError> {
+        match self {
+            ItemSource::Cached(entry) => entry.page(page),
+            ItemSource::Stored(stored) => {
+                let total = stored.index.len();
+                let (range, limit) = page_range(page, total, |cursor| stored.index.position(cursor))?;
+                let items = chunked::read_items(&mut stored.file, &stored.header, &stored.index, range.clone()).await?;
+                Ok(ItemPage::new(items, total, range, limit))
+            }
+        }
+    }
+}
+
+/// Memory budget of the collection cache as reported to admins.
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheWeight {
+    pub weight_bytes: u64,
+    pub capacity_bytes: u64,
+    pub ttl_secs: Option<u64>,
+    pub tti_secs: Option<u64>,
+    /// Bytes held by pre-encoded payloads, which have their own budget.
+    pub encoded_bytes: u64,
+}
+
+/// Upper bound on the memory held by pre-encoded collection payloads.
+const ENCODED_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Upper bound on the memory held by indexes of chunked collection files.
+const INDEX_CACHE_BYTES: u64 = 16 * 1024 * 1024;
+
 #[derive(Clone)]
 pub struct ContentStore {
-    cache: Cache<String, Arc<ContentCollection>>,
+    cache: Cache<String, Arc<CachedCollection>>,
+    /// Compressed JSON payloads keyed by content hash, so a rewritten
+    /// collection never serves a stale encoding.
+    encoded: Cache<(String, Encoding), Bytes>,
+    /// Item indexes of chunked files keyed by content hash, for reading
+    /// items of collections that are not cached.
+    indexes: Cache<String, Arc<ChunkIndex>>,
     storage_path: PathBuf,
     db: DbPool,
+    write_lock: Arc<Mutex<()>>,
+    metrics: Metrics,
+    /// Reads per alias since the last `flush_access`.
+    access: Arc<std::sync::Mutex<HashMap<String, u64>>>,
+    /// Size and modification time of the file last written for each alias,
+    /// so the watcher can tell the store's own writes from outside changes.
+    written: Arc<std::sync::Mutex<HashMap<String, (u64, SystemTime)>>>,
 }
 
 impl ContentStore {
-    pub fn new(storage_path: PathBuf, db: DbPool) -> Self {
-        let cache = Cache::builder()
-            .max_capacity(100)
-            .time_to_live(Duration::from_secs(3600))
+    /// Builds the store with the cache budget, TTL and TTI from `config`.
+    /// Collections are weighed by their decompressed size, so a few large
+    /// collections cannot crowd out the memory limit.
+    pub fn new(config: &Config, db: DbPool, metrics: Metrics) -> Self {
+        let evictions = metrics.cache_evictions.clone();
+        let mut builder = Cache::builder()
+            .weigher(|_alias, entry: &Arc<CachedCollection>| entry.size.try_into().unwrap_or(u32::MAX))
+            .max_capacity(config.cache_max_bytes)
+            .eviction_listener(move |_alias, _entry, cause| {
+                if cause.was_evicted() {
+                    evictions.inc();
+                }
+            });
+        if let Some(ttl) = config.cache_ttl {
+            builder = builder.time_to_live(ttl);
+        }
+        if let Some(tti) = config.cache_tti {
+            builder = builder.time_to_idle(tti);
+        }
+        let cache = builder.build();
+
+        let encoded = Cache::builder()
+            .weigher(|_key, value: &Bytes| value.len().try_into().unwrap_or(u32::MAX))
+            .max_capacity(ENCODED_CACHE_BYTES)
+            .time_to_idle(Duration::from_secs(3600))
+            .build();
+
+        let indexes = Cache::builder()
+            .weigher(|_hash, index: &Arc<ChunkIndex>| index.weight())
+            .max_capacity(INDEX_CACHE_BYTES)
+            .time_to_idle(Duration::from_secs(3600))
             .build();
 
         Self {
             cache,
-            storage_path,
+            encoded,
+            indexes,
+            storage_path: config.storage_path.clone(),
             db,
+            write_lock: Arc::new(Mutex::new(())),
+            metrics,
+            access: Default::default(),
+            written: Default::default(),
+        }
+    }
+
+    /// Number of collections currently held in the cache.
+    pub async fn cached_entries(&self) -> u64 {
+        self.cache.run_pending_tasks().await;
+        self.cache.entry_count()
+    }
+
+    /// Current weight and budget of the collection cache, in bytes.
+    pub async fn cache_weight(&self) -> CacheWeight {
+        self.cache.run_pending_tasks().await;
+        self.encoded.run_pending_tasks().await;
+        let policy = self.cache.policy();
+        CacheWeight {
+            weight_bytes: self.cache.weighted_size(),
+            capacity_bytes: policy.max_capacity().unwrap_or(u64::MAX),
+            ttl_secs: policy.time_to_live().map(|d| d.as_secs()),
+            tti_secs: policy.time_to_idle().map(|d| d.as_secs()),
+            encoded_bytes: self.encoded.weighted_size(),
         }
     }
 
     #[instrument(skip(self))]
-    pub async fn get_collection(&self, alias: &str) -> Result<Arc<ContentCollection>, AppError> {
+    pub async fn get_collection(&self, alias: &str) -> Result<Arc<CachedCollection>, AppError> {
         if let Some(cached) = self.cache.get(alias).await {
             debug!(alias, "Cache hit");
+            self.metrics.cache_hits.inc();
             return Ok(cached);
         }
 
-        debug!(alias, "Cache miss, loading from storage");
+        // Concurrent misses for the same alias wait on a single load and
+        // share its result, and count as one miss. A failed load is not
+        // cached, so the next request tries again.
+        let entry = self
+            .cache
+            .try_get_with_by_ref(alias, async {
+                debug!(alias, "Cache miss, loading from storage");
+                self.metrics.cache_misses.inc();
+                let meta = self.get_metadata(alias).await?;
+                let entry = self.load(meta, false).await?;
+                info!(alias, "Loaded collection from disk");
+                Ok::<_, AppError>(Arc::new(entry))
+            })
+            .await?;
+        Ok(entry)
+    }
+
+    /// Source for item reads of `alias`. A collection that is not cached is
+    /// read from its file without being loaded when the file is chunked;
+    /// legacy files are loaded into the cache as a whole.
+    #[instrument(skip(self))]
+    pub async fn item_source(&self, alias: &str) -> Result<ItemSource, AppError> {
+        if self.cache.contains_key(alias) {
+            return self.get_collection(alias).await.map(ItemSource::Cached);
+        }
 
         let meta = self.get_metadata(alias).await?;
+        let mut file = self.open_file(&meta).await?;
+        let Some(header) = chunked::read_header(&mut file).await? else {
+            return self.get_collection(alias).await.map(ItemSource::Cached);
+        };
+
+        let index = match self.indexes.get(&header.content_hash).await {
+            Some(index) => index,
+            None => {
+                let index = Arc::new(chunked::read_index(&mut file, &header).await?);
+                self.indexes.insert(header.content_hash.clone(), index.clone()).await;
+                index
+            }
+        };
+        debug!(alias, items = index.len(), "Reading items from chunked file");
+        Ok(ItemSource::Stored(Box::new(StoredCollection {
+            meta,
+            content_hash: header.content_hash.clone(),
+            file,
+            header,
+            index,
+        })))
+    }
+
+    /// Snapshot of the cached collections.
+    pub async fn cached(&self) -> Vec<Arc<CachedCollection>> {
+        self.cache.run_pending_tasks().await;
+        self.cache.iter().map(|(_, entry)| entry).collect()
+    }
+
+    /// Removes `alias` from the cache. Returns whether it was cached.
+    pub async fn evict(&self, alias: &str) -> bool {
+        self.cache.remove(alias).await.is_some()
+    }
+
+    /// Empties the collection cache and the pre-encoded payloads.
+    pub async fn clear(&self) {
+        self.cache.invalidate_all();
+        self.encoded.invalidate_all();
+        self.indexes.invalidate_all();
+        self.cache.run_pending_tasks().await;
+        self.encoded.run_pending_tasks().await;
+    }
+
+    /// Loads `aliases` into the cache, at most `concurrency` at a time.
+    pub async fn warm(&self, aliases: Vec<String>, concurrency: usize) -> Vec<(String, Result<(), AppError>)> {
+        futures::stream::iter(aliases)
+            .map(|alias| async move {
+                let result = self.get_collection(&alias).await.map(|_| ());
+                (alias, result)
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Counts a read of `alias` for the `recent` warm-up mode. Called by the
+    /// read routes once the collection has resolved, so warm-ups, writes and
+    /// unknown aliases are not counted.
+    pub fn record_access(&self, alias: &str) {
+        let mut access = self.access.lock().unwrap();
+        match access.get_mut(alias) {
+            Some(hits) => *hits += 1,
+            None => {
+                access.insert(alias.to_string(), 1);
+            }
+        }
+    }
+
+    /// Adds the reads recorded since the last flush to `collection_access`,
+    /// which ranks collections for the `recent` warm-up mode.
+    pub async fn flush_access(&self) -> Result<(), AppError> {
+        let access = std::mem::take(&mut *self.access.lock().unwrap());
+        if access.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self.db.begin().await?;
+        for (alias, hits) in &access {
+            sqlx::query(
+                "INSERT INTO collection_access (alias, hits, last_accessed_at) \
+                 SELECT alias, ?, datetime('now') FROM collections WHERE alias = ? \
+                 ON CONFLICT(alias) DO UPDATE SET hits = hits + excluded.hits, \
+                 last_accessed_at = excluded.last_accessed_at",
+            )
+            .bind(*hits as i64)
+            .bind(alias)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        debug!(collections = access.len(), "Flushed collection access counts");
+        Ok(())
+    }
+
+    /// Aliases flagged with `preload`.
+    pub async fn preload_aliases(&self) -> Result<Vec<String>, AppError> {
+        let aliases = sqlx::query_scalar("SELECT alias FROM collections WHERE preload = 1 ORDER BY alias")
+            .fetch_all(&self.db)
+            .await?;
+        Ok(aliases)
+    }
+
+    /// The `limit` most recently read aliases, busiest first on ties.
+    pub async fn recent_aliases(&self, limit: usize) -> Result<Vec<String>, AppError> {
+        let aliases = sqlx::query_scalar(
+            "SELECT a.alias FROM collection_access a JOIN collections c ON c.alias = a.alias \
+             ORDER BY a.last_accessed_at DESC, a.hits DESC LIMIT ?",
+        )
+        .bind(limit as i64)
+        .fetch_all(&self.db)
+        .await?;
+        Ok(aliases)
+    }
+
+    /// Reads and decompresses the stored file of `meta`, in either the
+    /// chunked or the legacy single-frame format. For legacy files the content
+    /// hash is taken from the row unless `rehash` is set or the row has none;
+    /// chunked files carry their own.
+    async fn load(&self, meta: CollectionMetadata, rehash: bool) -> Result<CachedCollection, AppError> {
         let file_path = self.storage_path.join(&meta.file_path);
 
         if !file_path.exists() {
@@ -74,36 +766,446 @@ impl ContentStore {
 
         let data = tokio::fs::read(&file_path).await?;
 
-        let collection = tokio::task::spawn_blocking(move || {
-            let mut decoder = FrameDecoder::new(&data[..]);
-            let mut decompressed = Vec::new();
-            decoder
-                .read_to_end(&mut decompressed)
-                .map_err(|e| AppError::Lz4(e.to_string()))?;
-            serde_json::from_slice::<ContentCollection>(&decompressed).map_err(AppError::from)
+        let known_hash = meta.content_hash.clone().filter(|_| !rehash);
+        let decompression_duration = self.metrics.decompression_duration.clone();
+        let compressed_len = data.len();
+        let (collection, content_hash, decompressed_len) = tokio::task::spawn_blocking(move || {
+            let started = Instant::now();
+            if chunked::is_chunked(&data) {
+                let (header, collection) = chunked::decode(&data)?;
+                decompression_duration.observe(started.elapsed().as_secs_f64());
+                return Ok((collection, header.content_hash, header.json_len as usize));
+            }
+            let decompressed = codec::decompress(&data)?;
+            decompression_duration.observe(started.elapsed().as_secs_f64());
+            let hash = known_hash.unwrap_or_else(|| codec::content_hash(&decompressed));
+            let collection = serde_json::from_slice::<ContentCollection>(&decompressed)?;
+            Ok::<_, AppError>((collection, hash, decompressed.len()))
         })
         .await
         .map_err(|e| AppError::TaskJoin(e.to_string()))??;
+        self.metrics.record_collection(&meta.alias, compressed_len, decompressed_len);
+
+        CachedCollection::new(meta, collection, content_hash, decompressed_len as u64)
+    }
+
+    /// Metadata of `alias` as held by its cached entry, without a query.
+    pub async fn cached_metadata(&self, alias: &str) -> Option<CollectionMetadata> {
+        self.cache.get(alias).await.map(|entry| entry.meta.clone())
+    }
+
+    /// Whether the cached copy of `meta.alias` was loaded for exactly this
+    /// row, as after a write through the store.
+    pub async fn is_current(&self, meta: &CollectionMetadata) -> bool {
+        self.cache.get(&meta.alias).await.is_some_and(|entry| entry.meta == *meta)
+    }
 
-        let arc_collection = Arc::new(collection);
-        self.cache
-            .insert(alias.to_string(), arc_collection.clone())
-            .await;
+    /// Drops the cached copy of `alias`; the next request reloads it.
+    pub async fn invalidate(&self, alias: &str) {
+        self.cache.invalidate(alias).await;
+    }
 
-        info!(alias, "Loaded collection from disk");
-        Ok(arc_collection)
+    /// Alias of the collection stored at `relative_path`, if any.
+    pub async fn alias_for_file(&self, relative_path: &str) -> Result<Option<String>, AppError> {
+        let alias = sqlx::query_scalar("SELECT alias FROM collections WHERE file_path = ?")
+            .bind(relative_path)
+            .fetch_optional(&self.db)
+            .await?;
+        Ok(alias)
     }
 
-    async fn get_metadata(&self, alias: &str) -> Result<CollectionMetadata, AppError> {
+    /// Picks up a stored file that was replaced outside the write API. When
+    /// the content differs from what the row records, the row's hash,
+    /// revision and search index are updated; a cached copy is replaced
+    /// eagerly, otherwise the collection is loaded on next use.
+    #[instrument(skip(self))]
+    pub async fn reload(&self, alias: &str) -> Result<(), AppError> {
+        let _guard = self.write_lock.lock().await;
+        let Some(meta) = self.find_metadata(alias).await? else {
+            self.cache.invalidate(alias).await;
+            return Ok(());
+        };
+
+        // The writer of a file this store wrote already cached what it
+        // holds; decoding it again would only replace a warm entry with an
+        // identical one.
+        let stamp = file_stamp(&self.storage_path.join(&meta.file_path)).await.ok();
+        if stamp.is_some() && self.written.lock().unwrap().get(alias) == stamp.as_ref() {
+            if !self.is_current(&meta).await {
+                self.cache.invalidate(alias).await;
+            }
+            return Ok(());
+        }
+
+        let mut entry = match self.load(meta, true).await {
+            Ok(entry) => entry,
+            Err(e) => {
+                self.cache.invalidate(alias).await;
+                return Err(e);
+            }
+        };
+
+        if entry.meta.content_hash.as_deref() != Some(entry.content_hash.as_str()) {
+            let mut tx = self.db.begin().await?;
+            sqlx::query(
+                "UPDATE collections SET content_hash = ?, updated_at = datetime('now'), \
+                 revision = revision + 1 WHERE alias = ?",
+            )
+            .bind(&entry.content_hash)
+            .bind(alias)
+            .execute(&mut *tx)
+            .await?;
+            SearchIndex::index_collection(&mut tx, alias, &entry.collection).await?;
+            tx.commit().await?;
+
+            entry.meta = self.get_metadata(alias).await?;
+            info!(alias, revision = entry.meta.revision, "Reloaded collection changed on disk");
+        }
+
+        if self.cache.contains_key(alias) {
+            self.cache.insert(alias.to_string(), Arc::new(entry)).await;
+        }
+        Ok(())
+    }
+
+    /// Returns the collection JSON compressed with `encoding`, compressing it
+    /// at most once per content hash. Concurrent requests for the same
+    /// variant wait on a single compression.
+    pub async fn encoded_collection(
+        &self,
+        entry: &CachedCollection,
+        encoding: Encoding,
+    ) -> Result<Bytes, AppError> {
+        let key = (entry.content_hash.clone(), encoding);
+        let collection = entry.collection.clone();
+        let alias = entry.meta.alias.clone();
+
+        self.encoded
+            .try_get_with(key, async move {
+                let bytes = tokio::task::spawn_blocking(move || {
+                    let json = serde_json::to_vec(&*collection)?;
+                    encoding.encode(&json)
+                })
+                .await
+                .map_err(std::io::Error::other)??;
+                info!(alias, encoding = encoding.as_str(), size = bytes.len(), "Encoded collection");
+                Ok::<_, std::io::Error>(Bytes::from(bytes))
+            })
+            .await
+            .map_err(|e| AppError::Io(std::io::Error::new(e.kind(), e.to_string())))
+    }
+
+    /// Opens the stored file of a collection for streaming when it is a
+    /// single LZ4 frame, i.e. in the legacy format. Chunked files are not a
+    /// valid `lz4` representation and yield `None`.
+    pub async fn open_stored(&self, meta: &CollectionMetadata) -> Result<Option<(tokio::fs::File, u64)>, AppError> {
+        use tokio::io::AsyncSeekExt;
+
+        let mut file = self.open_file(meta).await?;
+        if chunked::read_header(&mut file).await?.is_some() {
+            return Ok(None);
+        }
+        file.seek(std::io::SeekFrom::Start(0)).await?;
+        let len = file.metadata().await?.len();
+        Ok(Some((file, len)))
+    }
+
+    async fn open_file(&self, meta: &CollectionMetadata) -> Result<tokio::fs::File, AppError> {
+        let path = self.storage_path.join(&meta.file_path);
+        match tokio::fs::File::open(&path).await {
+            Ok(file) => Ok(file),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(AppError::NotFound(format!("Storage file not found: {:?}", path)))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn get_metadata(&self, alias: &str) -> Result<CollectionMetadata, AppError> {
+        self.find_metadata(alias).await?.ok_or_else(|| {
+            AppError::NotFound(format!("Collection metadata not found for alias: {}", alias))
+        })
+    }
+
+    async fn find_metadata(&self, alias: &str) -> Result<Option<CollectionMetadata>, AppError> {
         let result: Option<CollectionMetadata> =
-            sqlx::query_as("SELECT id, alias, name, file_path, language FROM collections WHERE alias = ?")
+            sqlx::query_as(&format!("{} WHERE alias = ?", SELECT_METADATA))
                 .bind(alias)
                 .fetch_optional(&self.db)
                 .await?;
+        Ok(result)
+    }
 
-        result.ok_or_else(|| {
-            AppError::NotFound(format!("Collection metadata not found for alias: {}", alias))
+    /// Compresses `upsert.content` into storage and registers it under `alias`.
+    #[instrument(skip(self, upsert))]
+    pub async fn save_collection(
+        &self,
+        alias: &str,
+        upsert: CollectionUpsert,
+        mode: WriteMode,
+    ) -> Result<CollectionMetadata, AppError> {
+        validate_alias(alias)?;
+        if let Some(group) = &upsert.translation_group {
+            validate_translation_group(group)?;
+        }
+        validate_collection(&upsert.content).map_err(AppError::BadRequest)?;
+
+        let _guard = self.write_lock.lock().await;
+        let existing = self.find_metadata(alias).await?;
+        match (mode, &existing) {
+            (WriteMode::Create, Some(_)) => {
+                return Err(AppError::Conflict(format!("Collection already exists: {}", alias)))
+            }
+            (WriteMode::Replace, None) => {
+                return Err(AppError::NotFound(format!("Collection not found: {}", alias)))
+            }
+            _ => {}
+        }
+
+        let row = RowValues {
+            name: &upsert.name,
+            language: upsert.language.as_deref(),
+            private: upsert.private,
+            cache_control: upsert.cache_control.as_deref(),
+            preload: upsert.preload,
+            translation_group: upsert.translation_group.as_deref(),
+            aligned_by_position: upsert.aligned_by_position,
+        };
+        self.persist(alias, row, &upsert.content, existing.as_ref()).await?;
+        self.cache.invalidate(alias).await;
+
+        info!(alias, items = upsert.content.items.len(), "Saved collection");
+        self.get_metadata(alias).await
+    }
+
+    /// Appends `item`, stamping `created_at` unless the item carries one.
+    pub async fn create_item(&self, alias: &str, mut item: ContentItem) -> Result<ContentItem, AppError> {
+        item.created_at.get_or_insert_with(Utc::now);
+        validate_item(&item).map_err(AppError::BadRequest)?;
+
+        self.modify_collection(alias, |collection, current| {
+            if current.index.contains_key(&item.id) {
+                return Err(AppError::Conflict(format!("Item {} already exists in {}", item.id, alias)));
+            }
+            collection.items.push(item.clone());
+            Ok(item)
         })
+        .await
+    }
+
+    pub async fn update_item(
+        &self,
+        alias: &str,
+        item_id: &str,
+        patch: ItemPatch,
+    ) -> Result<ContentItem, AppError> {
+        self.modify_collection(alias, |collection, current| {
+            let item = &mut collection.items[current.item_position(item_id)?];
+            if let Some(title) = patch.title {
+                item.title = title;
+            }
+            if let Some(body) = patch.body {
+                item.body = body;
+            }
+            if let Some(tags) = patch.tags {
+                item.tags = tags;
+            }
+            if let Some(authors) = patch.authors {
+                item.authors = authors;
+            }
+            if let Some(metadata) = patch.metadata {
+                item.metadata = metadata;
+            }
+            if let Some(position) = patch.position {
+                item.position = position;
+            }
+            item.updated_at = Some(Utc::now().max(item.created_at.unwrap_or_default()));
+            validate_item(item).map_err(AppError::BadRequest)?;
+            Ok(item.clone())
+        })
+        .await
+    }
+
+    pub async fn delete_item(&self, alias: &str, item_id: &str) -> Result<(), AppError> {
+        self.modify_collection(alias, |collection, current| {
+            collection.items.remove(current.item_position(item_id)?);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Applies `edit` to a copy of the stored collection, persists the result
+    /// as a new revision and swaps it into the cache. `edit` also gets the
+    /// current entry, whose item index matches the copy's positions.
+    #[instrument(skip(self, edit))]
+    async fn modify_collection<T>(
+        &self,
+        alias: &str,
+        edit: impl FnOnce(&mut ContentCollection, &CachedCollection) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        let _guard = self.write_lock.lock().await;
+        let current = self.get_collection(alias).await?;
+        let meta = self.get_metadata(alias).await?;
+        let mut collection = ContentCollection::clone(&current.collection);
+
+        let output = edit(&mut collection, &current)?;
+
+        let row = RowValues {
+            name: &meta.name,
+            language: meta.language.as_deref(),
+            private: meta.private,
+            cache_control: meta.cache_control.as_deref(),
+            preload: meta.preload,
+            translation_group: meta.translation_group.as_deref(),
+            aligned_by_position: meta.aligned_by_position,
+        };
+        let (content_hash, size) = self.persist(alias, row, &collection, Some(&meta)).await?;
+        let entry = CachedCollection::new(self.get_metadata(alias).await?, collection, content_hash, size)?;
+        info!(alias, revision = entry.meta.revision, "Updated collection items");
+        self.cache.insert(alias.to_string(), Arc::new(entry)).await;
+
+        Ok(output)
+    }
+
+    /// Writes `content` to storage and upserts its `collections` row, bumping
+    /// the revision of an existing row. Returns the new content hash and the
+    /// size of the JSON.
+    ///
+    /// The file is written to a temporary path and renamed into place inside
+    /// the transaction that updates the `collections` row and search index.
+    /// The file it replaces is kept under a backup name until the
+    /// transaction commits and restored if it does not, so a failed write
+    /// leaves both the old file and the old row untouched.
+    async fn persist(
+        &self,
+        alias: &str,
+        row: RowValues<'_>,
+        content: &ContentCollection,
+        existing: Option<&CollectionMetadata>,
+    ) -> Result<(String, u64), AppError> {
+        if let Some(value) = row.cache_control {
+            HeaderValue::from_str(value)
+                .map_err(|_| AppError::BadRequest(format!("Invalid cache_control: {}", value)))?;
+        }
+
+        let relative_path = format!("collections/{}.{}", alias, chunked::EXTENSION);
+        let owned = content.clone();
+        let encoded = tokio::task::spawn_blocking(move || chunked::encode(&owned))
+            .await
+            .map_err(|e| AppError::TaskJoin(e.to_string()))??;
+        let content_hash = encoded.content_hash;
+        let size = encoded.json_len;
+        let compressed = encoded.bytes;
+
+        let final_path = self.storage_path.join(&relative_path);
+        let temp_path = final_path.with_extension(format!("{}.tmp", chunked::EXTENSION));
+        if let Some(parent) = final_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        write_synced(&temp_path, &compressed).await?;
+        let backup_path = final_path.with_extension(format!("{}.bak", chunked::EXTENSION));
+        let backup = match back_up(&final_path, &backup_path).await {
+            Ok(backup) => backup,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&temp_path).await;
+                return Err(e.into());
+            }
+        };
+        let mut renamed = false;
+
+        let result = async {
+            let mut tx = self.db.begin().await?;
+            sqlx::query(
+                "INSERT INTO collections \
+                     (alias, name, file_path, language, private, cache_control, preload, translation_group, \
+                      aligned_by_position, content_hash, updated_at) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now')) \
+                 ON CONFLICT(alias) DO UPDATE SET name=excluded.name, file_path=excluded.file_path, \
+                 language=excluded.language, private=excluded.private, cache_control=excluded.cache_control, \
+                 preload=excluded.preload, translation_group=excluded.translation_group, \
+                 aligned_by_position=excluded.aligned_by_position, content_hash=excluded.content_hash, updated_at=excluded.updated_at, \
+                 revision=collections.revision + 1",
+            )
+            .bind(alias)
+            .bind(row.name)
+            .bind(&relative_path)
+            .bind(row.language)
+            .bind(row.private)
+            .bind(row.cache_control)
+            .bind(row.preload)
+            .bind(row.translation_group)
+            .bind(row.aligned_by_position)
+            .bind(&content_hash)
+            .execute(&mut *tx)
+            .await?;
+            SearchIndex::index_collection(&mut tx, alias, content).await?;
+            tokio::fs::rename(&temp_path, &final_path).await?;
+            renamed = true;
+            tx.commit().await?;
+            Ok::<_, AppError>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            if renamed {
+                let restored = if backup {
+                    tokio::fs::rename(&backup_path, &final_path).await
+                } else {
+                    tokio::fs::remove_file(&final_path).await
+                };
+                if let Err(restore) = restored {
+                    warn!(path = ?final_path, error = %restore, "Failed to restore collection file");
+                }
+            } else if backup {
+                let _ = tokio::fs::remove_file(&backup_path).await;
+            }
+            return Err(e);
+        }
+        if backup {
+            let _ = tokio::fs::remove_file(&backup_path).await;
+        }
+        if let Ok(stamp) = file_stamp(&final_path).await {
+            self.written.lock().unwrap().insert(alias.to_string(), stamp);
+        }
+
+        if let Some(old) = existing.filter(|old| old.file_path != relative_path) {
+            self.remove_file(&old.file_path).await;
+        }
+        Ok((content_hash, size))
+    }
+
+    #[instrument(skip(self))]
+    pub async fn delete_collection(&self, alias: &str) -> Result<(), AppError> {
+        let _guard = self.write_lock.lock().await;
+        let meta = self.get_metadata(alias).await?;
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query("DELETE FROM collections WHERE alias = ?")
+            .bind(alias)
+            .execute(&mut *tx)
+            .await?;
+        SearchIndex::remove_collection(&mut tx, alias).await?;
+        sqlx::query("DELETE FROM collection_access WHERE alias = ?")
+            .bind(alias)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        self.remove_file(&meta.file_path).await;
+        self.cache.invalidate(alias).await;
+        self.written.lock().unwrap().remove(alias);
+        self.metrics.forget_collection(alias);
+
+        info!(alias, "Deleted collection");
+        Ok(())
+    }
+
+    async fn remove_file(&self, relative_path: &str) {
+        let path = self.storage_path.join(relative_path);
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            warn!(path = ?path, error = %e, "Failed to remove collection file");
+        }
     }
 
     pub async fn get_item(
@@ -111,23 +1213,88 @@ impl ContentStore {
         collection_alias: &str,
         item_id: &str,
     ) -> Result<ContentItem, AppError> {
-        let collection = self.get_collection(collection_alias).await?;
-
-        collection
-            .items
-            .iter()
-            .find(|item| item.id == item_id)
-            .cloned()
-            .ok_or_else(|| {
-                AppError::NotFound(format!("Item {} not found in {}", item_id, collection_alias))
-            })
+        let entry = self.get_collection(collection_alias).await?;
+        entry.item(item_id).cloned()
+    }
+
+    /// Collections in the translation group of `meta`, including `meta`
+    /// itself, ordered by language.
+    pub async fn translations(&self, meta: &CollectionMetadata) -> Result<Vec<CollectionMetadata>, AppError> {
+        let Some(group) = &meta.translation_group else {
+            return Ok(vec![meta.clone()]);
+        };
+        let results: Vec<CollectionMetadata> = sqlx::query_as(&format!(
+            "{} WHERE translation_group = ? ORDER BY language, alias",
+            SELECT_METADATA
+        ))
+        .bind(group)
+        .fetch_all(&self.db)
+        .await?;
+        Ok(results)
     }
 
     pub async fn list_collections(&self) -> Result<Vec<CollectionMetadata>, AppError> {
         let results: Vec<CollectionMetadata> =
-            sqlx::query_as("SELECT id, alias, name, file_path, language FROM collections")
+            sqlx::query_as(SELECT_METADATA)
                 .fetch_all(&self.db)
                 .await?;
         Ok(results)
     }
 }
+
+fn is_slug(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 64
+        && value
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+fn validate_alias(alias: &str) -> Result<(), AppError> {
+    if is_slug(alias) {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!(
+            "Invalid alias '{}': use 1-64 lowercase letters, digits, '-' or '_'",
+            alias
+        )))
+    }
+}
+
+fn validate_translation_group(group: &str) -> Result<(), AppError> {
+    if is_slug(group) {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!(
+            "Invalid translation_group '{}': use 1-64 lowercase letters, digits, '-' or '_'",
+            group
+        )))
+    }
+}
+
+/// Size and modification time of the file at `path`.
+async fn file_stamp(path: &Path) -> std::io::Result<(u64, SystemTime)> {
+    let metadata = tokio::fs::metadata(path).await?;
+    Ok((metadata.len(), metadata.modified()?))
+}
+
+async fn write_synced(path: &Path, data: &[u8]) -> Result<(), AppError> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut file = tokio::fs::File::create(path).await?;
+    file.write_all(data).await?;
+    file.sync_all().await?;
+    Ok(())
+}
+
+/// Links the file at `path` to `backup` so it can be restored after `path`
+/// is replaced, copying it where hard links are unsupported. Returns false
+/// when there is no file to back up.
+async fn back_up(path: &Path, backup: &Path) -> std::io::Result<bool> {
+    let _ = tokio::fs::remove_file(backup).await;
+    match tokio::fs::hard_link(path, backup).await {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(_) => tokio::fs::copy(path, backup).await.map(|_| true),
+    }
+}
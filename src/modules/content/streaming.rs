@@ -0,0 +1,179 @@
+use axum::body::{Body, Bytes};
+use axum::http::{header::ACCEPT, HeaderMap};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use super::encoding::{Encoding, StreamEncoder};
+use super::query::FieldSet;
+use super::store::ContentCollection;
+
+/// Serialized items are flushed to the body in chunks of about this size,
+/// which bounds the memory a collection response holds at any time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks produced ahead of the client before the producer waits.
+const CHUNKS_AHEAD: usize = 2;
+
+pub const NDJSON: &str = "application/x-ndjson";
+
+/// Media types a whole collection can be served as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// The collection as one JSON document.
+    Json,
+    /// One item per line, without the collection's own fields.
+    Ndjson,
+}
+
+impl Format {
+    /// Picks the format from the request's `Accept`. NDJSON is only sent to
+    /// clients that name it and rate it at least as high as JSON.
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers.get(ACCEPT).and_then(|v| v.to_str().ok()) else {
+            return Format::Json;
+        };
+
+        let mut ndjson: Option<f32> = None;
+        let mut json: Option<f32> = None;
+        let mut wildcard: Option<f32> = None;
+        for part in accept.split(',') {
+            let mut pieces = part.split(';');
+            let media = pieces.next().unwrap_or_default().trim().to_ascii_lowercase();
+            let quality = pieces
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            match media.as_str() {
+                NDJSON => ndjson = Some(quality),
+                "application/json" => json = Some(quality),
+                "application/*" | "*/*" => wildcard = Some(wildcard.unwrap_or(0.0).max(quality)),
+                _ => {}
+            }
+        }
+
+        match ndjson {
+            Some(quality) if quality > 0.0 && quality >= json.or(wildcard).unwrap_or(0.0) => Format::Ndjson,
+            _ => Format::Json,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Ndjson => NDJSON,
+        }
+    }
+
+    /// A response body that serializes `collection` as items are sent,
+    /// each reduced to `fields`, and compresses it with `encoding` on the
+    /// way. With every field, the JSON variant produces the same bytes as
+    /// `serde_json::to_vec`.
+    ///
+    /// Serializing and compressing run on the blocking pool, a few chunks
+    /// ahead of the client, so a large download never holds up an async
+    /// worker. The producer stops when the client goes away.
+    pub fn body(self, collection: Arc<ContentCollection>, fields: FieldSet, encoding: Encoding) -> std::io::Result<Body> {
+        let mut writer = ChunkWriter {
+            collection,
+            format: self,
+            fields,
+            encoder: encoding.stream_encoder()?,
+            started: false,
+            position: 0,
+            finished: false,
+        };
+        let (tx, rx) = mpsc::channel(CHUNKS_AHEAD);
+        tokio::task::spawn_blocking(move || {
+            while let Some(chunk) = writer.next_chunk() {
+                if tx.blocking_send(chunk).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Body::from_stream(ReceiverStream::new(rx)))
+    }
+}
+
+struct ChunkWriter {
+    collection: Arc<ContentCollection>,
+    format: Format,
+    fields: FieldSet,
+    encoder: Option<StreamEncoder>,
+    /// Whether the collection's own fields have been written; they may
+    /// fill a chunk on their own.
+    started: bool,
+    position: usize,
+    finished: bool,
+}
+
+impl ChunkWriter {
+    fn next_chunk(&mut self) -> Option<std::io::Result<Bytes>> {
+        while !self.finished {
+            match self.produce() {
+                Ok(output) if output.is_empty() => {}
+                Ok(output) => return Some(Ok(Bytes::from(output))),
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+
+    /// Serializes the next items and passes them through the encoder. An
+    /// encoder may hold back all of its output until later chunks.
+    fn produce(&mut self) -> std::io::Result<Vec<u8>> {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE + CHUNK_SIZE / 4);
+        self.fill(&mut chunk)?;
+        let Some(encoder) = self.encoder.as_mut() else {
+            return Ok(chunk);
+        };
+        let mut output = encoder.write(&chunk)?;
+        if self.finished {
+            if let Some(encoder) = self.encoder.take() {
+                output.extend(encoder.finish()?);
+            }
+        }
+        Ok(output)
+    }
+
+    fn fill(&mut self, chunk: &mut Vec<u8>) -> Result<(), serde_json::Error> {
+        let items = &self.collection.items;
+        if !self.started && self.format == Format::Json {
+            chunk.extend_from_slice(b"{\"id\":");
+            serde_json::to_writer(&mut *chunk, &self.collection.id)?;
+            chunk.extend_from_slice(b",\"name\":");
+            serde_json::to_writer(&mut *chunk, &self.collection.name)?;
+            chunk.extend_from_slice(b",\"items\":[");
+        }
+        self.started = true;
+
+        while self.position < items.len() && chunk.len() < CHUNK_SIZE {
+            let item = self.fields.project(&items[self.position]);
+            match self.format {
+                Format::Json => {
+                    if self.position > 0 {
+                        chunk.push(b',');
+                    }
+                    serde_json::to_writer(&mut *chunk, &item)?;
+                }
+                Format::Ndjson => {
+                    serde_json::to_writer(&mut *chunk, &item)?;
+                    chunk.push(b'\n');
+                }
+            }
+            self.position += 1;
+        }
+
+        if self.position == items.len() {
+            if self.format == Format::Json {
+                chunk.extend_from_slice(b"]}");
+            }
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
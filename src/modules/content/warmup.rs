@@ -0,0 +1,97 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::error::AppError;
+use crate::AppState;
+
+/// How often read counts are written to `collection_access`.
+const ACCESS_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Which collections are loaded into the cache at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmupMode {
+    Off,
+    All,
+    /// Collections flagged with `preload`.
+    Preload,
+    /// The given number of most recently read collections.
+    Recent(usize),
+}
+
+impl FromStr for WarmupMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "off" => Ok(WarmupMode::Off),
+            "all" => Ok(WarmupMode::All),
+            "preload" => Ok(WarmupMode::Preload),
+            other => other
+                .strip_prefix("recent:")
+                .and_then(|n| n.parse().ok())
+                .map(WarmupMode::Recent)
+                .ok_or_else(|| format!("Unknown warm-up mode: {} (use off, all, preload or recent:<n>)", s)),
+        }
+    }
+}
+
+/// Starts loading the configured collections in the background. Readiness
+/// fails until the warm-up has finished, successfully or not.
+pub fn spawn(state: Arc<AppState>) -> JoinHandle<()> {
+    state.lifecycle.begin_warmup();
+    tokio::spawn(async move {
+        if let Err(e) = warm_up(&state).await {
+            warn!(error = %e, "Cache warm-up failed");
+        }
+        state.lifecycle.finish_warmup();
+    })
+}
+
+async fn warm_up(state: &AppState) -> Result<(), AppError> {
+    let store = &state.content_store;
+    let aliases = match state.config.warmup {
+        WarmupMode::Off => return Ok(()),
+        WarmupMode::All => store
+            .list_collections()
+            .await?
+            .into_iter()
+            .map(|meta| meta.alias)
+            .collect(),
+        WarmupMode::Preload => store.preload_aliases().await?,
+        WarmupMode::Recent(limit) => store.recent_aliases(limit).await?,
+    };
+
+    let started = Instant::now();
+    let requested = aliases.len();
+    let results = store.warm(aliases, state.config.warmup_concurrency).await;
+    for (alias, result) in &results {
+        if let Err(e) = result {
+            warn!(alias, error = %e, "Failed to warm collection");
+        }
+    }
+    info!(
+        mode = ?state.config.warmup,
+        requested,
+        loaded = results.iter().filter(|(_, r)| r.is_ok()).count(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "Cache warm-up finished"
+    );
+    Ok(())
+}
+
+/// Periodically persists read counts for the `recent` warm-up mode.
+pub fn spawn_access_flush(state: Arc<AppState>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(ACCESS_FLUSH_INTERVAL);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(e) = state.content_store.flush_access().await {
+                warn!(error = %e, "Failed to record collection access");
+            }
+        }
+    })
+}
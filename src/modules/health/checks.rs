@@ -0,0 +1,115 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::lifecycle::Lifecycle;
+use crate::db::{self, DbPool};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Fail,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckReport {
+    pub status: Status,
+    pub latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub status: Status,
+    pub checks: BTreeMap<&'static str, CheckReport>,
+}
+
+impl HealthReport {
+    pub fn is_ok(&self) -> bool {
+        self.status == Status::Ok
+    }
+}
+
+/// Runs the readiness checks concurrently, each bounded by `timeout`.
+pub async fn readiness(
+    lifecycle: &Lifecycle,
+    db: &DbPool,
+    storage_path: &Path,
+    timeout: Duration,
+) -> HealthReport {
+    let (lifecycle, database, storage, migrations) = tokio::join!(
+        run(timeout, check_lifecycle(lifecycle)),
+        run(timeout, check_database(db)),
+        run(timeout, check_storage(storage_path)),
+        run(timeout, check_migrations(db)),
+    );
+
+    let checks = BTreeMap::from([
+        ("lifecycle", lifecycle),
+        ("database", database),
+        ("storage", storage),
+        ("migrations", migrations),
+    ]);
+    let status = if checks.values().all(|c| c.status == Status::Ok) {
+        Status::Ok
+    } else {
+        Status::Fail
+    };
+    HealthReport { status, checks }
+}
+
+async fn run(timeout: Duration, check: impl Future<Output = Result<(), String>>) -> CheckReport {
+    let started = Instant::now();
+    let result = match tokio::time::timeout(timeout, check).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("timed out after {}ms", timeout.as_millis())),
+    };
+
+    CheckReport {
+        status: if result.is_ok() { Status::Ok } else { Status::Fail },
+        latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+        error: result.err(),
+    }
+}
+
+async fn check_lifecycle(lifecycle: &Lifecycle) -> Result<(), String> {
+    if lifecycle.is_draining() {
+        Err("shutting down".into())
+    } else if lifecycle.is_warming() {
+        Err("warming up".into())
+    } else {
+        Ok(())
+    }
+}
+
+async fn check_database(db: &DbPool) -> Result<(), String> {
+    sqlx::query("SELECT 1")
+        .execute(db)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+async fn check_storage(storage_path: &Path) -> Result<(), String> {
+    let mut entries = tokio::fs::read_dir(storage_path)
+        .await
+        .map_err(|e| format!("{}: {}", storage_path.display(), e))?;
+    entries
+        .next_entry()
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("{}: {}", storage_path.display(), e))
+}
+
+async fn check_migrations(db: &DbPool) -> Result<(), String> {
+    let pending = db::pending_migrations(db).await.map_err(|e| e.to_string())?;
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("pending migrations: {:?}", pending))
+    }
+}
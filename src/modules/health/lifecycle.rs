@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// A request that has started but not yet produced a response.
+#[derive(Debug, Clone)]
+pub struct InFlightRequest {
+    pub method: String,
+    pub path: String,
+    pub started: Instant,
+}
+
+/// Process lifecycle shared by readiness and shutdown: whether the instance
+/// is still warming up or already draining, and which requests are still
+/// being handled.
+#[derive(Debug, Default)]
+pub struct Lifecycle {
+    warming: AtomicBool,
+    draining: AtomicBool,
+    next_id: AtomicU64,
+    in_flight: Mutex<HashMap<u64, InFlightRequest>>,
+}
+
+impl Lifecycle {
+    /// Keeps readiness failing until `finish_warmup`.
+    pub fn begin_warmup(&self) {
+        self.warming.store(true, Ordering::SeqCst);
+    }
+
+    pub fn finish_warmup(&self) {
+        self.warming.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_warming(&self) -> bool {
+        self.warming.load(Ordering::SeqCst)
+    }
+
+    /// Marks the instance as shutting down so readiness starts failing.
+    pub fn begin_drain(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Registers a request until the returned guard is dropped, which also
+    /// covers requests whose connection goes away mid-flight.
+    pub fn track(&self, method: &str, path: &str) -> InFlightGuard<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = InFlightRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            started: Instant::now(),
+        };
+        self.in_flight.lock().unwrap().insert(id, request);
+        InFlightGuard { lifecycle: self, id }
+    }
+
+    /// Requests still in flight, oldest first.
+    pub fn in_flight(&self) -> Vec<InFlightRequest> {
+        let mut requests: Vec<_> = self.in_flight.lock().unwrap().values().cloned().collect();
+        requests.sort_by_key(|r| r.started);
+        requests
+    }
+
+    /// Logs every request that is still in flight, e.g. when the drain
+    /// timeout expires.
+    pub fn log_in_flight(&self) {
+        let requests = self.in_flight();
+        tracing::warn!(count = requests.len(), "Requests still in flight");
+        for request in requests {
+            tracing::warn!(
+                method = %request.method,
+                path = %request.path,
+                elapsed_ms = request.started.elapsed().as_millis() as u64,
+                "In-flight request"
+            );
+        }
+    }
+
+    pub fn in_flight_count(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    /// Requests accepted since startup.
+    pub fn total_requests(&self) -> u64 {
+        self.next_id.load(Ordering::Relaxed)
+    }
+}
+
+pub struct InFlightGuard<'a> {
+    lifecycle: &'a Lifecycle,
+    id: u64,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.lifecycle.in_flight.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Resolves on SIGTERM or SIGINT.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("Received SIGINT"),
+        _ = terminate => tracing::info!("Received SIGTERM"),
+    }
+}
@@ -0,0 +1,7 @@
+pub mod checks;
+pub mod lifecycle;
+pub mod routes;
+
+pub use checks::{CheckReport, HealthReport};
+pub use lifecycle::Lifecycle;
+pub use routes::routes;
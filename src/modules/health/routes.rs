@@ -0,0 +1,35 @@
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use super::checks::{self, HealthReport};
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/health/live", get(live))
+        .route("/health/ready", get(ready))
+}
+
+/// The process is up and serving requests; no dependencies are checked.
+async fn live() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+/// Ready to take traffic: 200 when every check passes, 503 otherwise.
+async fn ready(State(state): State<Arc<AppState>>) -> (StatusCode, Json<HealthReport>) {
+    let report = checks::readiness(
+        &state.lifecycle,
+        &state.db,
+        &state.config.storage_path,
+        state.config.health_check_timeout,
+    )
+    .await;
+
+    let status = if report.is_ok() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
@@ -0,0 +1,5 @@
+pub mod registry;
+pub mod routes;
+
+pub use registry::Metrics;
+pub use routes::routes;
@@ -0,0 +1,145 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+use crate::db::DbPool;
+
+/// Prometheus collectors for the HTTP layer, the content cache and the
+/// database pool. Cloning shares the underlying collectors.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_duration: HistogramVec,
+    pub cache_hits: IntCounter,
+    pub cache_misses: IntCounter,
+    pub cache_evictions: IntCounter,
+    pub cache_entries: IntGauge,
+    pub cache_weight: IntGauge,
+    pub decompression_duration: Histogram,
+    pub collection_bytes: IntGaugeVec,
+    pub db_connections: IntGaugeVec,
+    pub db_max_connections: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("tulpar".into()), None).expect("valid metrics prefix");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route and status"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let cache_hits = IntCounter::new("content_cache_hits_total", "Collection cache hits").unwrap();
+        let cache_misses = IntCounter::new("content_cache_misses_total", "Collection cache misses").unwrap();
+        let cache_evictions = IntCounter::new(
+            "content_cache_evictions_total",
+            "Collections evicted from the cache by size or expiry",
+        )
+        .unwrap();
+        let cache_entries = IntGauge::new("content_cache_entries", "Collections currently cached").unwrap();
+        let cache_weight = IntGauge::new(
+            "content_cache_weight_bytes",
+            "Decompressed size of the cached collections",
+        )
+        .unwrap();
+        let decompression_duration = Histogram::with_opts(
+            HistogramOpts::new("content_decompression_duration_seconds", "Time spent decompressing collections")
+                .buckets(vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]),
+        )
+        .unwrap();
+        let collection_bytes = IntGaugeVec::new(
+            Opts::new("content_collection_bytes", "Size of the last loaded version of a collection"),
+            &["collection", "encoding"],
+        )
+        .unwrap();
+        let db_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "SQLite pool connections by state"),
+            &["state"],
+        )
+        .unwrap();
+        let db_max_connections =
+            IntGauge::new("db_pool_max_connections", "Configured SQLite pool size").unwrap();
+
+        let metrics = Self {
+            registry,
+            http_requests,
+            http_duration,
+            cache_hits,
+            cache_misses,
+            cache_evictions,
+            cache_entries,
+            cache_weight,
+            decompression_duration,
+            collection_bytes,
+            db_connections,
+            db_max_connections,
+        };
+        metrics.register();
+        metrics
+    }
+
+    fn register(&self) {
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(self.http_requests.clone()),
+            Box::new(self.http_duration.clone()),
+            Box::new(self.cache_hits.clone()),
+            Box::new(self.cache_misses.clone()),
+            Box::new(self.cache_evictions.clone()),
+            Box::new(self.cache_entries.clone()),
+            Box::new(self.cache_weight.clone()),
+            Box::new(self.decompression_duration.clone()),
+            Box::new(self.collection_bytes.clone()),
+            Box::new(self.db_connections.clone()),
+            Box::new(self.db_max_connections.clone()),
+        ];
+        for collector in collectors {
+            self.registry.register(collector).expect("metric registered twice");
+        }
+    }
+
+    /// Records the sizes of a freshly loaded collection.
+    pub fn record_collection(&self, alias: &str, compressed: usize, decompressed: usize) {
+        self.collection_bytes
+            .with_label_values(&[alias, "lz4"])
+            .set(compressed as i64);
+        self.collection_bytes
+            .with_label_values(&[alias, "identity"])
+            .set(decompressed as i64);
+    }
+
+    pub fn forget_collection(&self, alias: &str) {
+        let _ = self.collection_bytes.remove_label_values(&[alias, "lz4"]);
+        let _ = self.collection_bytes.remove_label_values(&[alias, "identity"]);
+    }
+
+    /// Samples the pool gauges and renders every metric in the Prometheus
+    /// text format.
+    pub fn render(&self, db: &DbPool) -> String {
+        let size = db.size() as i64;
+        let idle = db.num_idle() as i64;
+        self.db_connections.with_label_values(&["idle"]).set(idle);
+        self.db_connections.with_label_values(&["active"]).set(size - idle);
+        self.db_max_connections
+            .set(db.options().get_max_connections() as i64);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding into a Vec cannot fail");
+        String::from_utf8(buffer).expect("Prometheus text format is UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,37 @@
+use axum::{
+    extract::State,
+    http::header::CONTENT_TYPE,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use std::sync::Arc;
+
+use crate::error::AppError;
+use crate::modules::auth::{Principal, Scope};
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/metrics", get(metrics))
+}
+
+/// Requires the `admin` scope: labels name every collection and route,
+/// including private collections.
+async fn metrics(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, AppError> {
+    principal.require(Scope::Admin)?;
+    state
+        .metrics
+        .cache_entries
+        .set(state.content_store.cached_entries().await as i64);
+    state
+        .metrics
+        .cache_weight
+        .set(state.content_store.cache_weight().await.weight_bytes as i64);
+    Ok((
+        [(CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        state.metrics.render(&state.db),
+    ))
+}
@@ -1 +1,6 @@
+pub mod admin;
+pub mod auth;
 pub mod content;
+pub mod health;
+pub mod metrics;
+pub mod search;
@@ -0,0 +1,166 @@
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::modules::auth::Principal;
+use crate::modules::content::store::ContentCollection;
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use tracing::{debug, instrument};
+
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+pub const MAX_SEARCH_LIMIT: usize = 100;
+
+/// Placed around matches by `snippet()` and turned into `<mark>` tags once
+/// the surrounding text is escaped.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub collection: Option<String>,
+    pub language: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct SearchHit {
+    pub collection: String,
+    pub item_id: String,
+    pub title: String,
+    pub snippet: String,
+    pub score: f64,
+}
+
+/// Full-text index over item titles and bodies, backed by the SQLite FTS5
+/// `content_search` table. Rows are keyed by collection alias and are only
+/// returned while the alias is still registered in `collections`.
+#[derive(Clone)]
+pub struct SearchIndex {
+    db: DbPool,
+}
+
+impl SearchIndex {
+    pub fn new(db: DbPool) -> Self {
+        Self { db }
+    }
+
+    /// Searches the collections `principal` may read; private collections are
+    /// only included when the principal has a grant for them.
+    #[instrument(skip(self, principal))]
+    pub async fn search(&self, query: &SearchQuery, principal: &Principal) -> Result<Vec<SearchHit>, AppError> {
+        let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        if limit == 0 || limit > MAX_SEARCH_LIMIT {
+            return Err(AppError::BadRequest(format!(
+                "limit must be between 1 and {}",
+                MAX_SEARCH_LIMIT
+            )));
+        }
+
+        let match_expr = match_expression(&query.q)
+            .ok_or_else(|| AppError::BadRequest("Search query must not be empty".into()))?;
+
+        let (all_private, granted) = if principal.can_access("*") {
+            (true, Vec::new())
+        } else {
+            (false, principal.collections.clone().unwrap_or_default())
+        };
+
+        let mut hits: Vec<SearchHit> = sqlx::query_as(
+            "SELECT content_search.alias AS collection, content_search.item_id, content_search.title, \
+                    snippet(content_search, -1, char(2), char(3), '…', 16) AS snippet, \
+                    -bm25(content_search, 0.0, 0.0, 5.0, 1.0) AS score \
+             FROM content_search \
+             JOIN collections ON collections.alias = content_search.alias \
+             WHERE content_search MATCH ? \
+               AND (? IS NULL OR content_search.alias = ?) \
+               AND (? IS NULL OR collections.language = ?) \
+               AND (collections.private = 0 OR ? OR collections.alias IN (SELECT value FROM json_each(?))) \
+             ORDER BY score DESC \
+             LIMIT ?",
+        )
+        .bind(&match_expr)
+        .bind(&query.collection)
+        .bind(&query.collection)
+        .bind(&query.language)
+        .bind(&query.language)
+        .bind(all_private)
+        .bind(serde_json::to_string(&granted)?)
+        .bind(limit as i64)
+        .fetch_all(&self.db)
+        .await?;
+
+        for hit in &mut hits {
+            hit.snippet = highlight(&hit.snippet);
+        }
+
+        debug!(hits = hits.len(), "Search completed");
+        Ok(hits)
+    }
+
+    /// Replaces the indexed items of `alias` with the items of `collection`.
+    /// Runs on the caller's connection so it can share a transaction with the
+    /// `collections` row update.
+    pub async fn index_collection(
+        conn: &mut SqliteConnection,
+        alias: &str,
+        collection: &ContentCollection,
+    ) -> Result<(), AppError> {
+        Self::remove_collection(&mut *conn, alias).await?;
+
+        for item in &collection.items {
+            sqlx::query("INSERT INTO content_search (alias, item_id, title, body) VALUES (?, ?, ?, ?)")
+                .bind(alias)
+                .bind(&item.id)
+                .bind(&item.title)
+                .bind(&item.body)
+                .execute(&mut *conn)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn remove_collection(conn: &mut SqliteConnection, alias: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM content_search WHERE alias = ?")
+            .bind(alias)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Turns free text into an FTS5 expression that matches every term, quoting
+/// each one so user input can never be parsed as FTS5 query syntax.
+fn match_expression(q: &str) -> Option<String> {
+    let terms: Vec<String> = q
+        .split_whitespace()
+        .map(|term| term.replace('"', ""))
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"", term))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// The snippet as HTML: item text escaped, so stored markup is shown rather
+/// than rendered, and matches wrapped in `<mark>`.
+fn highlight(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len() + 32);
+    for c in snippet.chars() {
+        match c {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
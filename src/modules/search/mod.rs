@@ -0,0 +1,5 @@
+pub mod index;
+pub mod routes;
+
+pub use index::SearchIndex;
+pub use routes::routes;
@@ -0,0 +1,25 @@
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use std::sync::Arc;
+
+use super::index::{SearchHit, SearchQuery};
+use crate::error::AppError;
+use crate::modules::auth::{Principal, Scope};
+use crate::AppState;
+
+pub fn routes() -> Router<Arc<AppState>> {
+    Router::new().route("/search", get(search))
+}
+
+async fn search(
+    principal: Principal,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<SearchHit>>, AppError> {
+    principal.require(Scope::Read)?;
+    let hits = state.search.search(&query, &principal).await?;
+    Ok(Json(hits))
+}
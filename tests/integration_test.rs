@@ -1,10 +1,13 @@
 use axum_test::TestServer;
-use tulpar_api::{create_router, db, AppState};
+use tulpar_api::modules::auth::Scope;
+use tulpar_api::modules::content::store::ContentCollection;
+use tulpar_api::modules::search::SearchIndex;
+use tulpar_api::{config::Config, create_router, db, AppState};
 use std::sync::Arc;
 use tempfile::tempdir;
 use std::fs;
 use lz4_flex::frame::FrameEncoder;
-use std::io::Write;
+use std::io::{Read, Write};
 use serde_json::json;
 
 #[tokio::test]
@@ -52,7 +55,7 @@ async fn test_full_api_flow() {
         .await
         .expect("Failed to insert seed data");
 
-    let state = Arc::new(AppState::new(pool, storage_path));
+    let state = Arc::new(AppState::new(pool, test_config(storage_path)).expect("Invalid configuration"));
     let app = create_router(state);
     let server = TestServer::new(app).expect("Failed to create test server");
 
@@ -89,3 +92,1562 @@ async fn test_full_api_flow() {
         .await;
     assert!(response.headers().contains_key("x-request-id"));
 }
+
+async fn setup_collection(alias: &str, content: &serde_json::Value) -> (tempfile::TempDir, Arc<AppState>, TestServer) {
+    let temp_dir = tempdir().expect("Failed to create temp dir");
+    let storage_path = temp_dir.path().to_path_buf();
+    let collections_dir = storage_path.join("collections");
+    fs::create_dir_all(&collections_dir).expect("Failed to create collections dir");
+
+    let database_url = format!("sqlite:{}", temp_dir.path().join("test.db").to_str().unwrap());
+    let pool = db::establish_connection(&database_url).await.expect("Failed to connect to DB");
+    db::run_migrations(&pool).await.expect("Failed to run migrations");
+
+    let filename = format!("{}.json.lz4", alias);
+    let output_file = fs::File::create(collections_dir.join(&filename)).expect("Failed to create test file");
+    let mut encoder = FrameEncoder::new(output_file);
+    encoder.write_all(serde_json::to_string(content).unwrap().as_bytes()).expect("Failed to compress");
+    encoder.finish().expect("Failed to finish compression");
+
+    sqlx::query("INSERT INTO collections (alias, name, file_path, language) VALUES (?, ?, ?, ?)")
+        .bind(alias)
+        .bind(content["name"].as_str().unwrap_or(alias))
+        .bind(format!("collections/{}", filename))
+        .bind("en")
+        .execute(&pool)
+        .await
+        .expect("Failed to insert seed data");
+
+    let state = Arc::new(AppState::new(pool, test_config(storage_path)).expect("Invalid configuration"));
+    let server = TestServer::new(create_router(state.clone())).expect("Failed to create test server");
+    (temp_dir, state, server)
+}
+
+/// In-process test servers have no peer address, which IP rate limits need;
+/// tests that don't exercise limits turn them off.
+fn test_config(storage_path: std::path::PathBuf) -> Config {
+    Config {
+        storage_path,
+        rate_limit_ip: None,
+        rate_limit_key: None,
+        ..Config::default()
+    }
+}
+
+async fn api_key(state: &AppState, scopes: &[Scope]) -> http::HeaderValue {
+    let (_, key) = state.api_keys.create("test", scopes).await.expect("Failed to create API key");
+    http::HeaderValue::from_str(&format!("Bearer {}", key)).unwrap()
+}
+
+fn numbered_collection(count: usize) -> serde_json::Value {
+    let items: Vec<_> = (1..=count)
+        .map(|i| json!({ "id": format!("item-{}", i), "title": format!("Item {}", i), "body": format!("Body {}", i) }))
+        .collect();
+    json!({ "id": "coll-1", "name": "Numbered", "items": items })
+}
+
+#[tokio::test]
+async fn test_item_pagination() {
+    let (_dir, _state, server) = setup_collection("numbered", &numbered_collection(5)).await;
+    let ip = http::HeaderValue::from_static("127.0.0.2");
+    let xff = http::header::HeaderName::from_static("x-forwarded-for");
+
+    let response = server.get("/api/v1/content/collections/numbered/items?limit=2")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    response.assert_status_ok();
+    let page: serde_json::Value = response.json();
+    assert_eq!(page["total"], 5);
+    assert_eq!(page["items"].as_array().unwrap().len(), 2);
+    assert_eq!(page["items"][0]["id"], "item-1");
+    assert_eq!(page["next_cursor"], "item-2");
+
+    let response = server.get("/api/v1/content/collections/numbered/items?limit=2&cursor=item-4")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    let page: serde_json::Value = response.json();
+    assert_eq!(page["items"][0]["id"], "item-5");
+    assert!(page["next_cursor"].is_null());
+
+    let response = server.get("/api/v1/content/collections/numbered/items?offset=3&limit=1")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    let page: serde_json::Value = response.json();
+    assert_eq!(page["offset"], 3);
+    assert_eq!(page["items"][0]["id"], "item-4");
+    assert_eq!(page["next_cursor"], "item-4");
+
+    let response = server.get("/api/v1/content/collections/numbered/items?cursor=missing")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    response.assert_status_bad_request();
+    let error: serde_json::Value = response.json();
+    assert_eq!(error["code"], "INVALID_QUERY");
+}
+
+#[tokio::test]
+async fn test_search() {
+    let content = json!({
+        "id": "poems",
+        "name": "Poems",
+        "items": [
+            { "id": "1", "title": "Morning", "body": "Awake! for Morning in the Bowl of Night" },
+            { "id": "2", "title": "The Tavern", "body": "I heard a Voice within the Tavern cry" },
+            { "id": "3", "title": "Night", "body": "The Moving Finger writes; and, having writ, moves on" },
+            { "id": "4", "title": "Bread", "body": "A <b>Jug</b> of <script>Wine</script> & Thou" }
+        ]
+    });
+    let (_dir, state, server) = setup_collection("poems", &content).await;
+    let collection: ContentCollection = serde_json::from_value(content).unwrap();
+    let mut conn = state.db.acquire().await.unwrap();
+    SearchIndex::index_collection(&mut conn, "poems", &collection).await.unwrap();
+    drop(conn);
+
+    let xff = http::header::HeaderName::from_static("x-forwarded-for");
+    let ip = http::HeaderValue::from_static("127.0.0.3");
+
+    let response = server.get("/api/v1/content/search?q=tavern")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    response.assert_status_ok();
+    let hits: serde_json::Value = response.json();
+    let hits = hits.as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["collection"], "poems");
+    assert_eq!(hits[0]["item_id"], "2");
+    assert!(hits[0]["snippet"].as_str().unwrap().contains("<mark>Tavern</mark>"));
+
+    let response = server.get("/api/v1/content/search?q=jug")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    let hits: serde_json::Value = response.json();
+    assert_eq!(
+        hits[0]["snippet"],
+        "A &lt;b&gt;<mark>Jug</mark>&lt;/b&gt; of &lt;script&gt;Wine&lt;/script&gt; &amp; Thou",
+        "stored markup is escaped"
+    );
+
+    let response = server.get("/api/v1/content/search?q=night")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    let hits: serde_json::Value = response.json();
+    let ids: Vec<_> = hits.as_array().unwrap().iter().map(|h| h["item_id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec!["3", "1"], "title matches should rank first");
+
+    let response = server.get("/api/v1/content/search?q=night&language=tr")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    let hits: serde_json::Value = response.json();
+    assert!(hits.as_array().unwrap().is_empty());
+
+    let response = server.get("/api/v1/content/search?q=%22%20%22")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_collection_write_api() {
+    let (dir, state, server) = setup_collection("numbered", &numbered_collection(1)).await;
+    let xff = http::header::HeaderName::from_static("x-forwarded-for");
+    let auth = api_key(&state, &[Scope::Admin]).await;
+
+    let new_collection = json!({
+        "alias": "poems",
+        "name": "Poems",
+        "language": "en",
+        "content": { "id": "poems", "name": "Poems", "items": [{ "id": "1", "title": "One", "body": "First verse" }] }
+    });
+
+    let response = server.post("/api/v1/content/collections")
+        .add_header(xff.clone(), http::HeaderValue::from_static("127.0.0.4"))
+        .json(&new_collection)
+        .await;
+    response.assert_status_unauthorized();
+
+    let response = server.post("/api/v1/content/collections")
+        .add_header(xff.clone(), http::HeaderValue::from_static("127.0.0.4"))
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&new_collection)
+        .await;
+    response.assert_status(http::StatusCode::CREATED);
+    let meta: serde_json::Value = response.json();
+    assert_eq!(meta["file_path"], "collections/poems.tlpc");
+    assert!(dir.path().join("collections/poems.tlpc").exists());
+
+    let response = server.post("/api/v1/content/collections")
+        .add_header(xff.clone(), http::HeaderValue::from_static("127.0.0.4"))
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&new_collection)
+        .await;
+    response.assert_status(http::StatusCode::CONFLICT);
+
+    let entry = state.content_store.get_collection("poems").await.unwrap();
+    assert_eq!(entry.collection.items[0].body, "First verse");
+
+    let response = server.put("/api/v1/content/collections/poems")
+        .add_header(xff.clone(), http::HeaderValue::from_static("127.0.0.5"))
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({
+            "name": "Poems (revised)",
+            "language": "en",
+            "content": { "id": "poems", "name": "Poems", "items": [{ "id": "1", "title": "One", "body": "Revised verse" }] }
+        }))
+        .await;
+    response.assert_status_ok();
+    let entry = state.content_store.get_collection("poems").await.unwrap();
+    assert_eq!(entry.collection.items[0].body, "Revised verse", "cache entry should be invalidated on update");
+
+    let response = server.get("/api/v1/content/search?q=revised")
+        .add_header(xff.clone(), http::HeaderValue::from_static("127.0.0.5"))
+        .await;
+    let hits: serde_json::Value = response.json();
+    assert_eq!(hits[0]["collection"], "poems");
+
+    let response = server.delete("/api/v1/content/collections/poems")
+        .add_header(xff.clone(), http::HeaderValue::from_static("127.0.0.5"))
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status(http::StatusCode::NO_CONTENT);
+    assert!(!dir.path().join("collections/poems.tlpc").exists());
+
+    let response = server.get("/api/v1/content/collections/poems")
+        .add_header(xff.clone(), http::HeaderValue::from_static("127.0.0.6"))
+        .await;
+    response.assert_status_not_found();
+
+    let response = server.post("/api/v1/content/collections")
+        .add_header(xff.clone(), http::HeaderValue::from_static("127.0.0.6"))
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "alias": "Bad Alias", "name": "x", "content": { "id": "x", "name": "x", "items": [] } }))
+        .await;
+    response.assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_item_write_api() {
+    let (dir, state, server) = setup_collection("numbered", &numbered_collection(2)).await;
+    let xff = http::header::HeaderName::from_static("x-forwarded-for");
+    let ip = http::HeaderValue::from_static("127.0.0.7");
+    let auth = api_key(&state, &[Scope::Write]).await;
+
+    let response = server.post("/api/v1/content/collections/numbered/items")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "id": "item-3", "title": "Item 3", "body": "Body 3" }))
+        .await;
+    response.assert_status(http::StatusCode::CREATED);
+
+    let response = server.patch("/api/v1/content/collections/numbered/items/item-1")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "title": "Item One" }))
+        .await;
+    response.assert_status_ok();
+    let item: serde_json::Value = response.json();
+    assert_eq!(item["title"], "Item One");
+    assert_eq!(item["body"], "Body 1");
+
+    let response = server.delete("/api/v1/content/collections/numbered/items/item-2")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status(http::StatusCode::NO_CONTENT);
+
+    let response = server.post("/api/v1/content/collections/numbered/items")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "id": "item-1", "title": "Duplicate", "body": "" }))
+        .await;
+    response.assert_status(http::StatusCode::CONFLICT);
+
+    let entry = state.content_store.get_collection("numbered").await.unwrap();
+    let ids: Vec<_> = entry.collection.items.iter().map(|item| item.id.as_str()).collect();
+    assert_eq!(ids, vec!["item-1", "item-3"]);
+
+    let collections = state.content_store.list_collections().await.unwrap();
+    let meta = collections.iter().find(|c| c.alias == "numbered").unwrap();
+    assert_eq!(meta.revision, 4);
+    assert!(!dir.path().join("collections/numbered.tlpc.tmp").exists());
+    assert!(!dir.path().join("collections/numbered.json.lz4").exists(), "legacy file is replaced on write");
+
+    let reloaded = tulpar_api::modules::content::ContentStore::new(&test_config(dir.path().to_path_buf()), state.db.clone(), Default::default());
+    let item = reloaded.get_item("numbered", "item-1").await.unwrap();
+    assert_eq!(item.title, "Item One", "edits should be persisted to storage");
+}
+
+#[tokio::test]
+async fn test_api_key_scopes() {
+    let (dir, state, server) = setup_collection("numbered", &numbered_collection(1)).await;
+    let xff = http::header::HeaderName::from_static("x-forwarded-for");
+    let ip = http::HeaderValue::from_static("127.0.0.8");
+    let read_key = api_key(&state, &[Scope::Read]).await;
+    let write_key = api_key(&state, &[Scope::Write]).await;
+
+    let response = server.post("/api/v1/content/collections/numbered/items")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, read_key.clone())
+        .json(&json!({ "id": "item-2", "title": "Item 2", "body": "Body 2" }))
+        .await;
+    response.assert_status_forbidden();
+
+    let response = server.delete("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, write_key.clone())
+        .await;
+    response.assert_status_forbidden();
+
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, http::HeaderValue::from_static("Bearer tlp_bogus"))
+        .await;
+    response.assert_status_unauthorized();
+
+    let (record, key) = state.api_keys.create("revoked", &[Scope::Admin]).await.unwrap();
+    state.api_keys.revoke(record.id).await.unwrap();
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::HeaderName::from_static("x-api-key"), http::HeaderValue::from_str(&key).unwrap())
+        .await;
+    response.assert_status_unauthorized();
+
+    let private = Arc::new(AppState::new(state.db.clone(), Config {
+        public_read: false,
+        ..test_config(dir.path().to_path_buf())
+    }).expect("Invalid configuration"));
+    let private_server = TestServer::new(create_router(private)).unwrap();
+    let ip = http::HeaderValue::from_static("127.0.0.9");
+
+    let response = private_server.get("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    response.assert_status_unauthorized();
+
+    let (_, key) = state.api_keys.create("reader", &[Scope::Read]).await.unwrap();
+    let response = private_server.get("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::HeaderName::from_static("x-api-key"), http::HeaderValue::from_str(&key).unwrap())
+        .await;
+    response.assert_status_ok();
+}
+
+fn jwt(secret: &str, scope: &str, collections: &[&str]) -> http::HeaderValue {
+    let exp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() + 600;
+    let claims = json!({ "sub": "user-1", "exp": exp, "scope": scope, "collections": collections });
+    let token = jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_secret(secret.as_bytes()),
+    )
+    .unwrap();
+    http::HeaderValue::from_str(&format!("Bearer {}", token)).unwrap()
+}
+
+#[tokio::test]
+async fn test_jwt_and_private_collections() {
+    let (dir, state, _server) = setup_collection("numbered", &numbered_collection(1)).await;
+    let secret = "test-jwt-secret";
+    let missing_jwks = AppState::new(state.db.clone(), Config {
+        jwt_jwks_path: Some(dir.path().join("missing-jwks.json")),
+        ..test_config(dir.path().to_path_buf())
+    });
+    assert!(missing_jwks.is_err());
+    let state = Arc::new(AppState::new(state.db.clone(), Config {
+        jwt_secret: Some(secret.into()),
+        ..test_config(dir.path().to_path_buf())
+    }).expect("Invalid configuration"));
+    let server = TestServer::new(create_router(state.clone())).unwrap();
+    let xff = http::header::HeaderName::from_static("x-forwarded-for");
+    let ip = http::HeaderValue::from_static("127.0.0.10");
+
+    let response = server.post("/api/v1/content/collections")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, jwt(secret, "read write", &["diary"]))
+        .json(&json!({
+            "alias": "diary",
+            "name": "Diary",
+            "private": true,
+            "content": { "id": "diary", "name": "Diary", "items": [{ "id": "1", "title": "Secret", "body": "Hidden entry" }] }
+        }))
+        .await;
+    response.assert_status(http::StatusCode::CREATED);
+
+    let response = server.get("/api/v1/content/collections/diary")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    response.assert_status_unauthorized();
+
+    let response = server.get("/api/v1/content/collections/diary/items/1")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, jwt(secret, "read", &["numbered"]))
+        .await;
+    response.assert_status_forbidden();
+
+    let response = server.get("/api/v1/content/collections/diary/items/1")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, jwt(secret, "read", &["diary"]))
+        .await;
+    response.assert_status_ok();
+
+    let ip = http::HeaderValue::from_static("127.0.0.11");
+    let response = server.get("/api/v1/content/collections")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    let collections: serde_json::Value = response.json();
+    assert!(collections.as_array().unwrap().iter().all(|c| c["alias"] != "diary"));
+
+    let response = server.get("/api/v1/content/search?q=hidden")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    let hits: serde_json::Value = response.json();
+    assert!(hits.as_array().unwrap().is_empty());
+
+    let response = server.get("/api/v1/content/search?q=hidden")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, jwt(secret, "read", &["*"]))
+        .await;
+    let hits: serde_json::Value = response.json();
+    assert_eq!(hits[0]["collection"], "diary");
+
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, jwt("wrong-secret", "read", &[]))
+        .await;
+    response.assert_status_unauthorized();
+}
+
+#[tokio::test]
+async fn test_conditional_requests() {
+    let (_dir, state, server) = setup_collection("numbered", &numbered_collection(2)).await;
+    let xff = http::header::HeaderName::from_static("x-forwarded-for");
+    let ip = http::HeaderValue::from_static("127.0.0.12");
+
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    response.assert_status_ok();
+    let etag = response.header(http::header::ETAG);
+    let last_modified = response.header(http::header::LAST_MODIFIED);
+    assert_eq!(response.header(http::header::CACHE_CONTROL), "public, max-age=60");
+
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::IF_NONE_MATCH, etag.clone())
+        .await;
+    response.assert_status(http::StatusCode::NOT_MODIFIED);
+    assert!(response.as_bytes().is_empty());
+    assert_eq!(response.header(http::header::ETAG), etag);
+
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::IF_MODIFIED_SINCE, last_modified)
+        .await;
+    response.assert_status(http::StatusCode::NOT_MODIFIED);
+
+    let response = server.get("/api/v1/content/collections/numbered/items/item-2")
+        .add_header(xff.clone(), ip.clone())
+        .await;
+    let item_etag = response.header(http::header::ETAG);
+    assert_ne!(item_etag, etag);
+    assert!(item_etag.to_str().unwrap().starts_with("W/\""), "items may be compressed by the layer");
+
+    let response = server.get("/api/v1/content/collections/numbered/items/item-2")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::IF_NONE_MATCH, item_etag)
+        .await;
+    response.assert_status(http::StatusCode::NOT_MODIFIED);
+
+    let ip = http::HeaderValue::from_static("127.0.0.13");
+    let response = server.put("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::AUTHORIZATION, api_key(&state, &[Scope::Write]).await)
+        .json(&json!({
+            "name": "Numbered",
+            "cache_control": "public, max-age=3600, immutable",
+            "content": { "id": "coll-1", "name": "Numbered", "items": [] }
+        }))
+        .await;
+    response.assert_status_ok();
+    assert!(response.json::<serde_json::Value>()["content_hash"].is_string());
+
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::IF_NONE_MATCH, etag.clone())
+        .await;
+    response.assert_status_ok();
+    assert_ne!(response.header(http::header::ETAG), etag);
+    assert_eq!(response.header(http::header::CACHE_CONTROL), "public, max-age=3600, immutable");
+}
+
+#[tokio::test]
+async fn test_pre_encoded_collection_responses() {
+    let (_dir, _state, server) = setup_collection("numbered", &numbered_collection(50)).await;
+    let xff = http::header::HeaderName::from_static("x-forwarded-for");
+    let ip = http::HeaderValue::from_static("127.0.0.14");
+    let path = "/api/v1/content/collections/numbered";
+
+    let response = server.get(path)
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::ACCEPT_ENCODING, http::HeaderValue::from_static("gzip;q=0.5, br"))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header(http::header::CONTENT_ENCODING), "br");
+    assert_eq!(response.header(http::header::VARY), "accept, accept-encoding, accept-language");
+    let mut json = Vec::new();
+    brotli::Decompressor::new(response.as_bytes().as_ref(), 4096).read_to_end(&mut json).unwrap();
+    let collection: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(collection["items"][49]["id"], "item-50");
+    let br_etag = response.header(http::header::ETAG);
+
+    let response = server.get(path)
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::ACCEPT_ENCODING, http::HeaderValue::from_static("gzip"))
+        .await;
+    assert_eq!(response.header(http::header::CONTENT_ENCODING), "gzip");
+    assert_ne!(response.header(http::header::ETAG), br_etag);
+    let mut json = Vec::new();
+    flate2::read::GzDecoder::new(response.as_bytes().as_ref()).read_to_end(&mut json).unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&json).unwrap(), collection);
+
+    let response = server.get(path)
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::ACCEPT_ENCODING, http::HeaderValue::from_static("lz4"))
+        .await;
+    assert_eq!(response.header(http::header::CONTENT_ENCODING), "lz4");
+    let mut json = Vec::new();
+    lz4_flex::frame::FrameDecoder::new(response.as_bytes().as_ref()).read_to_end(&mut json).unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&json).unwrap(), collection);
+
+    let ip = http::HeaderValue::from_static("127.0.0.15");
+    let response = server.get(path)
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::ACCEPT_ENCODING, http::HeaderValue::from_static("*"))
+        .await;
+    assert_eq!(response.header(http::header::CONTENT_ENCODING), "br", "wildcard must not select lz4");
+
+    let response = server.get(path)
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::ACCEPT_ENCODING, http::HeaderValue::from_static("br"))
+        .add_header(http::header::IF_NONE_MATCH, br_etag)
+        .await;
+    response.assert_status(http::StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn test_response_compression_layer() {
+    let (dir, state, _server) = setup_collection("numbered", &numbered_collection(100)).await;
+    let state = Arc::new(AppState::new(state.db.clone(), Config {
+        compression: vec![tulpar_api::modules::content::encoding::Encoding::Gzip],
+        compression_min_size: 256,
+        ..test_config(dir.path().to_path_buf())
+    }).expect("Invalid configuration"));
+    let server = TestServer::new(create_router(state)).unwrap();
+    let xff = http::header::HeaderName::from_static("x-forwarded-for");
+    let ip = http::HeaderValue::from_static("127.0.0.16");
+    let gzip = http::HeaderValue::from_static("gzip");
+
+    let response = server.get("/api/v1/content/collections/numbered/items?limit=100")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::ACCEPT_ENCODING, gzip.clone())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header(http::header::CONTENT_ENCODING), "gzip");
+    let mut json = Vec::new();
+    flate2::read::GzDecoder::new(response.as_bytes().as_ref()).read_to_end(&mut json).unwrap();
+    let page: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(page["total"], 100);
+
+    let response = server.get("/api/v1/content/collections/numbered/items/item-1")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::ACCEPT_ENCODING, gzip.clone())
+        .await;
+    assert!(response.maybe_header(http::header::CONTENT_ENCODING).is_none(), "small responses stay uncompressed");
+
+    let response = server.get("/api/v1/content/collections/numbered/items?limit=100")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::ACCEPT_ENCODING, http::HeaderValue::from_static("br"))
+        .await;
+    assert!(response.maybe_header(http::header::CONTENT_ENCODING).is_none(), "disabled algorithms are not used");
+
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(xff.clone(), ip.clone())
+        .add_header(http::header::ACCEPT_ENCODING, http::HeaderValue::from_static("br, gzip;q=0.1"))
+        .await;
+    assert_eq!(response.header(http::header::CONTENT_ENCODING), "gzip");
+    assert!(response.header(http::header::ETAG).to_str().unwrap().ends_with("-gzip\""));
+}
+
+#[tokio::test]
+async fn test_rate_limiting() {
+    use tulpar_api::config::RateLimit;
+    use tulpar_api::middleware::RouteGroup;
+
+    let (dir, state, _server) = setup_collection("numbered", &numbered_collection(3)).await;
+    let state = Arc::new(AppState::new(state.db.clone(), Config {
+        rate_limit_ip: Some(RateLimit { per_second: 1, burst: 2 }),
+        rate_limit_key: Some(RateLimit { per_second: 1, burst: 3 }),
+        rate_limit_groups: vec![
+            (RouteGroup::Search, RateLimit { per_second: 1, burst: 1 }),
+            (RouteGroup::Admin, RateLimit { per_second: 1, burst: 1 }),
+        ],
+        trusted_proxies: vec!["127.0.0.0/8".parse().unwrap()],
+        ..test_config(dir.path().to_path_buf())
+    }).expect("Invalid configuration"));
+    let app = create_router(state.clone()).into_make_service_with_connect_info::<std::net::SocketAddr>();
+    let server = TestServer::new(app).unwrap();
+    let xff = http::header::HeaderName::from_static("x-forwarded-for");
+
+    let client = http::HeaderValue::from_static("203.0.113.1");
+    let response = server.get("/api/v1/content/collections").add_header(xff.clone(), client.clone()).await;
+    response.assert_status_ok();
+    assert_eq!(response.header("ratelimit-limit"), "2");
+    assert_eq!(response.header("ratelimit-remaining"), "1");
+    server.get("/api/v1/content/collections").add_header(xff.clone(), client.clone()).await.assert_status_ok();
+
+    let response = server.get("/api/v1/content/collections").add_header(xff.clone(), client.clone()).await;
+    response.assert_status(http::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.header(http::header::RETRY_AFTER), "1");
+    assert_eq!(response.header("ratelimit-remaining"), "0");
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["code"], "RATE_LIMITED");
+
+    // The trusted proxy forwards another client, which has its own bucket;
+    // hops appended by the proxy chain itself are skipped.
+    let response = server.get("/api/v1/content/collections")
+        .add_header(xff.clone(), http::HeaderValue::from_static("198.51.100.7, 127.0.0.9"))
+        .await;
+    response.assert_status_ok();
+
+    // Route group limits apply on top of the client limit.
+    let other = http::HeaderValue::from_static("203.0.113.2");
+    server.get("/api/v1/content/search?q=body").add_header(xff.clone(), other.clone()).await.assert_status_ok();
+    let response = server.get("/api/v1/content/search?q=body").add_header(xff.clone(), other.clone()).await;
+    response.assert_status(http::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.header("ratelimit-limit"), "1");
+
+    // Groups follow the matched route, not the path: reading a collection
+    // aliased `admin` or `search` stays in the read group.
+    for alias in ["admin", "search"] {
+        state.content_store
+            .save_collection(alias, serde_json::from_value(json!({ "name": alias, "content": numbered_collection(1) })).unwrap(), tulpar_api::modules::content::store::WriteMode::Create)
+            .await
+            .unwrap();
+        let reader = http::HeaderValue::from_str(&format!("203.0.113.{}", alias.len())).unwrap();
+        for path in [format!("/api/v1/content/collections/{}", alias), format!("/api/v1/content/collections/{}/items/item-1", alias)] {
+            let response = server.get(&path).add_header(xff.clone(), reader.clone()).await;
+            response.assert_status_ok();
+            assert_eq!(response.header("ratelimit-limit"), "2", "{}", path);
+        }
+    }
+
+    // API keys are limited per key, independent of the address.
+    let key = api_key(&state, &[Scope::Read]).await;
+    let response = server.get("/api/v1/content/collections")
+        .add_header(http::header::AUTHORIZATION, key.clone())
+        .add_header(xff.clone(), client.clone())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header("ratelimit-limit"), "3");
+
+    // Failed authentications are limited per address before credentials
+    // are checked, independent of the anonymous bucket.
+    let attacker = http::HeaderValue::from_static("203.0.113.9");
+    let garbage = http::HeaderValue::from_static("Bearer not-a-key");
+    for _ in 0..2 {
+        server.get("/api/v1/content/collections")
+            .add_header(http::header::AUTHORIZATION, garbage.clone())
+            .add_header(xff.clone(), attacker.clone())
+            .await
+            .assert_status(http::StatusCode::UNAUTHORIZED);
+    }
+    for credentials in [garbage.clone(), key.clone()] {
+        server.get("/api/v1/content/collections")
+            .add_header(http::header::AUTHORIZATION, credentials)
+            .add_header(xff.clone(), attacker.clone())
+            .await
+            .assert_status(http::StatusCode::TOO_MANY_REQUESTS);
+    }
+    server.get("/api/v1/content/collections")
+        .add_header(xff.clone(), attacker.clone())
+        .await
+        .assert_status_ok();
+
+    // Without a trusted proxy, forwarded headers are ignored and every
+    // client behind the same peer shares its bucket.
+    let state = Arc::new(AppState::new(state.db.clone(), Config {
+        rate_limit_ip: Some(RateLimit { per_second: 1, burst: 2 }),
+        ..test_config(dir.path().to_path_buf())
+    }).expect("Invalid configuration"));
+    let app = create_router(state.clone()).into_make_service_with_connect_info::<std::net::SocketAddr>();
+    let server = TestServer::new(app).unwrap();
+    for ip in ["203.0.113.3", "203.0.113.4"] {
+        server.get("/api/v1/content/collections")
+            .add_header(xff.clone(), http::HeaderValue::from_static(ip))
+            .await
+            .assert_status_ok();
+    }
+    let response = server.get("/api/v1/content/collections")
+        .add_header(xff.clone(), http::HeaderValue::from_static("203.0.113.5"))
+        .await;
+    response.assert_status(http::StatusCode::TOO_MANY_REQUESTS);
+
+    // Without connection info, IP limits refuse requests rather than
+    // putting every client into one bucket.
+    let state = Arc::new(AppState::new(state.db.clone(), Config {
+        rate_limit_ip: Some(RateLimit { per_second: 1, burst: 2 }),
+        ..test_config(dir.path().to_path_buf())
+    }).expect("Invalid configuration"));
+    let server = TestServer::new(create_router(state)).unwrap();
+    let response = server.get("/api/v1/content/collections").await;
+    response.assert_status(http::StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(response.json::<serde_json::Value>()["code"], "INTERNAL_ERROR");
+
+    // Health probes are neither authenticated nor limited.
+    for _ in 0..5 {
+        server.get("/health/live")
+            .add_header(http::header::AUTHORIZATION, http::HeaderValue::from_static("Bearer not-a-key"))
+            .await
+            .assert_status_ok();
+    }
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint() {
+    let (_dir, state, server) = setup_collection("numbered", &numbered_collection(3)).await;
+
+    server.get("/api/v1/content/collections/numbered").await.assert_status_ok();
+    server.get("/api/v1/content/collections/numbered/items/item-2").await.assert_status_ok();
+    server.get("/api/v1/content/collections/missing").await.assert_status_not_found();
+
+    server.get("/metrics").await.assert_status_unauthorized();
+    server.get("/metrics")
+        .add_header(http::header::AUTHORIZATION, api_key(&state, &[Scope::Read]).await)
+        .await
+        .assert_status_forbidden();
+    let response = server.get("/metrics")
+        .add_header(http::header::AUTHORIZATION, api_key(&state, &[Scope::Admin]).await)
+        .await;
+    response.assert_status_ok();
+    assert!(response.header(http::header::CONTENT_TYPE).to_str().unwrap().starts_with("text/plain"));
+    let text = response.text();
+
+    assert!(text.contains(r#"tulpar_http_requests_total{method="GET",route="/api/v1/content/collections/:alias",status="200"} 1"#));
+    assert!(text.contains(r#"tulpar_http_requests_total{method="GET",route="/api/v1/content/collections/:alias",status="404"} 1"#));
+    assert!(text.contains(r#"tulpar_http_request_duration_seconds_count{method="GET",route="/api/v1/content/collections/:alias/items/:item_id",status="200"} 1"#));
+    assert!(text.contains("tulpar_content_cache_misses_total 2"), "unknown aliases count as misses");
+    assert!(text.contains("tulpar_content_cache_hits_total 1"));
+    assert!(text.contains("tulpar_content_cache_entries 1"));
+    assert!(text.contains("tulpar_content_cache_weight_bytes 190"));
+    assert!(text.contains("tulpar_content_decompression_duration_seconds_count 1"));
+    assert!(text.contains(r#"tulpar_content_collection_bytes{collection="numbered",encoding="identity"}"#));
+    assert!(text.contains(r#"tulpar_content_collection_bytes{collection="numbered",encoding="lz4"}"#));
+    assert!(text.contains(r#"tulpar_db_pool_connections{state="idle"}"#));
+    assert!(text.contains("tulpar_db_pool_max_connections 5"));
+}
+
+#[tokio::test]
+async fn test_health_checks() {
+    let (dir, state, server) = setup_collection("numbered", &numbered_collection(1)).await;
+
+    let response = server.get("/health/live").await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["status"], "ok");
+
+    let response = server.get("/health/ready").await;
+    response.assert_status_ok();
+    let report: serde_json::Value = response.json();
+    assert_eq!(report["status"], "ok");
+    for check in ["database", "storage", "migrations"] {
+        assert_eq!(report["checks"][check]["status"], "ok", "{} check failed", check);
+        assert!(report["checks"][check]["latency_ms"].is_number());
+    }
+
+    sqlx::query("DELETE FROM _sqlx_migrations WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)")
+        .execute(&state.db)
+        .await
+        .unwrap();
+    let state = Arc::new(AppState::new(state.db.clone(), test_config(dir.path().join("missing"))).expect("Invalid configuration"));
+    let server = TestServer::new(create_router(state)).unwrap();
+
+    let response = server.get("/health/ready").await;
+    response.assert_status(http::StatusCode::SERVICE_UNAVAILABLE);
+    let report: serde_json::Value = response.json();
+    assert_eq!(report["status"], "fail");
+    assert_eq!(report["checks"]["database"]["status"], "ok");
+    assert_eq!(report["checks"]["storage"]["status"], "fail");
+    assert_eq!(report["checks"]["migrations"]["status"], "fail");
+    assert!(report["checks"]["migrations"]["error"].as_str().unwrap().contains("pending"));
+
+    server.get("/health/live").await.assert_status_ok();
+}
+
+#[tokio::test]
+async fn test_readiness_fails_while_draining() {
+    let (_dir, state, server) = setup_collection("numbered", &numbered_collection(1)).await;
+
+    let report: serde_json::Value = server.get("/health/ready").await.json();
+    assert_eq!(report["checks"]["lifecycle"]["status"], "ok");
+    assert_eq!(state.lifecycle.in_flight_count(), 0);
+
+    state.lifecycle.begin_drain();
+
+    let response = server.get("/health/ready").await;
+    response.assert_status(http::StatusCode::SERVICE_UNAVAILABLE);
+    let report: serde_json::Value = response.json();
+    assert_eq!(report["checks"]["lifecycle"]["status"], "fail");
+    assert_eq!(report["checks"]["lifecycle"]["error"], "shutting down");
+    assert_eq!(report["checks"]["database"]["status"], "ok");
+
+    // Requests already routed to the instance are still served.
+    server.get("/api/v1/content/collections/numbered").await.assert_status_ok();
+    server.get("/health/live").await.assert_status_ok();
+    assert_eq!(state.lifecycle.in_flight_count(), 0);
+    assert_eq!(state.lifecycle.total_requests(), 4);
+}
+
+#[tokio::test]
+async fn test_hot_reload() {
+    let (dir, state, _server) = setup_collection("numbered", &numbered_collection(2)).await;
+    let state = Arc::new(AppState::new(state.db.clone(), Config {
+        collections_poll_interval: Some(std::time::Duration::from_millis(50)),
+        ..test_config(dir.path().to_path_buf())
+    }).expect("Invalid configuration"));
+    let _reloader = tulpar_api::modules::content::Reloader::spawn(state.clone()).unwrap();
+    let server = TestServer::new(create_router(state.clone())).unwrap();
+
+    let response = server.get("/api/v1/content/collections/numbered/items/item-1").await;
+    let etag = response.header(http::header::ETAG);
+    assert_eq!(response.json::<serde_json::Value>()["title"], "Item 1");
+
+    // Replace the stored file behind the server's back.
+    let mut changed = numbered_collection(2);
+    changed["items"][0]["title"] = json!("Changed on disk");
+    let path = dir.path().join("collections/numbered.json.lz4");
+    let temp = dir.path().join("numbered.tmp");
+    let mut encoder = FrameEncoder::new(fs::File::create(&temp).unwrap());
+    encoder.write_all(serde_json::to_string(&changed).unwrap().as_bytes()).unwrap();
+    encoder.finish().unwrap();
+    fs::rename(&temp, &path).unwrap();
+
+    let mut reloaded = false;
+    for _ in 0..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let response = server.get("/api/v1/content/collections/numbered/items/item-1").await;
+        if response.json::<serde_json::Value>()["title"] == "Changed on disk" {
+            assert_ne!(response.header(http::header::ETAG), etag, "ETag follows the new content");
+            reloaded = true;
+            break;
+        }
+    }
+    assert!(reloaded, "collection was not reloaded after its file changed");
+
+    let meta = state.content_store.get_metadata("numbered").await.unwrap();
+    assert_eq!(meta.revision, 2);
+    let hits: Vec<serde_json::Value> = server.get("/api/v1/content/search?q=changed").await.json();
+    assert_eq!(hits[0]["item_id"], "item-1");
+
+    // Row changes made by another process drop the cached metadata.
+    sqlx::query("UPDATE collections SET private = 1 WHERE alias = 'numbered'")
+        .execute(&state.db)
+        .await
+        .unwrap();
+    let mut hidden = false;
+    for _ in 0..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        if server.get("/api/v1/content/collections/numbered/items/item-1").await.status_code() == http::StatusCode::UNAUTHORIZED {
+            hidden = true;
+            break;
+        }
+    }
+    assert!(hidden, "private flag set in the database was not picked up");
+
+    // The server's own writes are neither reloaded by the watcher nor
+    // dropped by the poller.
+    let key = api_key(&state, &[Scope::Write]).await;
+    server.patch("/api/v1/content/collections/numbered/items/item-2")
+        .add_header(http::header::AUTHORIZATION, key.clone())
+        .json(&json!({ "title": "Changed by the API" }))
+        .await
+        .assert_status_ok();
+    let loads = state.metrics.decompression_duration.get_sample_count();
+    let misses = state.metrics.cache_misses.get();
+    tokio::time::sleep(std::time::Duration::from_millis(600)).await;
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(http::header::AUTHORIZATION, key)
+        .await;
+    assert_eq!(response.json::<ContentCollection>().items[1].title, "Changed by the API");
+    assert_eq!(state.metrics.decompression_duration.get_sample_count(), loads, "no reload after an API write");
+    assert_eq!(state.metrics.cache_misses.get(), misses, "the written entry stays cached");
+}
+
+#[tokio::test]
+async fn test_admin_cache_endpoints() {
+    let (_dir, state, server) = setup_collection("numbered", &numbered_collection(3)).await;
+    let admin = api_key(&state, &[Scope::Admin]).await;
+    let writer = api_key(&state, &[Scope::Write]).await;
+    let auth = http::header::AUTHORIZATION;
+
+    server.get("/api/v1/admin/cache").await.assert_status(http::StatusCode::UNAUTHORIZED);
+    server.get("/api/v1/admin/cache").add_header(auth.clone(), writer.clone()).await
+        .assert_status(http::StatusCode::FORBIDDEN);
+
+    let listing: serde_json::Value = server.get("/api/v1/admin/cache").add_header(auth.clone(), admin.clone()).await.json();
+    assert_eq!(listing["entries"], 0);
+
+    let response = server.post("/api/v1/admin/cache/warm")
+        .add_header(auth.clone(), admin.clone())
+        .json(&json!({ "aliases": ["numbered", "missing"] }))
+        .await;
+    response.assert_status_ok();
+    let warmed: serde_json::Value = response.json();
+    assert_eq!(warmed["loaded"], json!(["numbered"]));
+    assert_eq!(warmed["failed"][0]["alias"], "missing");
+
+    let listing: serde_json::Value = server.get("/api/v1/admin/cache").add_header(auth.clone(), admin.clone()).await.json();
+    assert_eq!(listing["entries"], 1);
+    let entry = &listing["collections"][0];
+    assert_eq!(entry["alias"], "numbered");
+    assert_eq!(entry["items"], 3);
+    assert!(entry["size_bytes"].as_u64().unwrap() > 0);
+    assert_eq!(listing["weight_bytes"], entry["size_bytes"]);
+    assert_eq!(listing["capacity_bytes"], 256 * 1024 * 1024);
+    assert_eq!(listing["ttl_secs"], 3600);
+    assert!(entry["age_secs"].is_u64());
+
+    server.delete("/api/v1/admin/cache/numbered").add_header(auth.clone(), admin.clone()).await
+        .assert_status(http::StatusCode::NO_CONTENT);
+    server.delete("/api/v1/admin/cache/numbered").add_header(auth.clone(), admin.clone()).await
+        .assert_status_not_found();
+
+    server.get("/api/v1/content/collections/numbered").await.assert_status_ok();
+    server.delete("/api/v1/admin/cache").add_header(auth.clone(), writer.clone()).await
+        .assert_status(http::StatusCode::FORBIDDEN);
+    server.delete("/api/v1/admin/cache").add_header(auth.clone(), admin.clone()).await
+        .assert_status(http::StatusCode::NO_CONTENT);
+    let listing: serde_json::Value = server.get("/api/v1/admin/cache").add_header(auth.clone(), admin.clone()).await.json();
+    assert_eq!(listing["entries"], 0);
+}
+
+#[tokio::test]
+async fn test_cache_is_weighted_by_size() {
+    let (dir, state, _server) = setup_collection("small", &numbered_collection(2)).await;
+    let large = numbered_collection(200);
+    state.content_store
+        .save_collection("large", serde_json::from_value(json!({ "name": "Large", "content": large })).unwrap(), tulpar_api::modules::content::store::WriteMode::Create)
+        .await
+        .unwrap();
+    let large_size = serde_json::to_vec(&large).unwrap().len() as u64;
+
+    // The budget fits the large collection or the small one, not both.
+    let state = Arc::new(AppState::new(state.db.clone(), Config {
+        cache_max_bytes: large_size + 100,
+        cache_tti: Some(std::time::Duration::from_secs(600)),
+        ..test_config(dir.path().to_path_buf())
+    }).expect("Invalid configuration"));
+    let store = &state.content_store;
+    store.get_collection("small").await.unwrap();
+    store.get_collection("large").await.unwrap();
+
+    let weight = store.cache_weight().await;
+    assert!(weight.weight_bytes <= large_size + 100, "weight {} exceeds the budget", weight.weight_bytes);
+    assert_eq!(weight.capacity_bytes, large_size + 100);
+    assert_eq!(weight.tti_secs, Some(600));
+    let cached: Vec<String> = store.cached().await.iter().map(|e| e.meta.alias.clone()).collect();
+    assert!(cached.len() < 2, "both collections cached within a budget for one: {:?}", cached);
+}
+
+#[tokio::test]
+async fn test_startup_warmup() {
+    use tulpar_api::modules::content::warmup::{self, WarmupMode};
+
+    let (dir, state, server) = setup_collection("numbered", &numbered_collection(3)).await;
+    let key = api_key(&state, &[Scope::Write]).await;
+    for (alias, preload) in [("preloaded", true), ("other", false)] {
+        server.post("/api/v1/content/collections")
+            .add_header(http::header::AUTHORIZATION, key.clone())
+            .json(&json!({ "alias": alias, "name": alias, "preload": preload, "content": numbered_collection(2) }))
+            .await
+            .assert_status(http::StatusCode::CREATED);
+    }
+
+    let warmed = |mode: WarmupMode| {
+        let db = state.db.clone();
+        let path = dir.path().to_path_buf();
+        async move {
+            let state = Arc::new(AppState::new(db, Config { warmup: mode, ..test_config(path) }).expect("Invalid configuration"));
+            let server = TestServer::new(create_router(state.clone())).unwrap();
+            let handle = warmup::spawn(state.clone());
+            assert!(state.lifecycle.is_warming());
+            let report: serde_json::Value = server.get("/health/ready").await.json();
+            if state.lifecycle.is_warming() {
+                assert_eq!(report["checks"]["lifecycle"]["error"], "warming up");
+            }
+            handle.await.unwrap();
+            server.get("/health/ready").await.assert_status_ok();
+
+            let mut aliases: Vec<String> = state.content_store.cached().await.iter().map(|e| e.meta.alias.clone()).collect();
+            aliases.sort();
+            aliases
+        }
+    };
+
+    assert_eq!(warmed(WarmupMode::Preload).await, vec!["preloaded"]);
+    assert_eq!(warmed(WarmupMode::Off).await, Vec::<String>::new());
+    // The migration-seeded row has no file in the test storage and is skipped.
+    assert_eq!(warmed(WarmupMode::All).await, vec!["numbered", "other", "preloaded"]);
+
+    // Reads through the API are recorded and rank collections for the recent
+    // mode; warm-ups, writes and unknown aliases are not.
+    state.content_store.warm(vec!["numbered".into(), "preloaded".into()], 2).await;
+    server.get("/api/v1/content/collections/other").await.assert_status_ok();
+    server.get("/api/v1/content/collections/other/items/item-1").await.assert_status_ok();
+    server.get("/api/v1/content/collections/no-such-alias").await.assert_status_not_found();
+    server.patch("/api/v1/content/collections/numbered/items/item-1")
+        .add_header(http::header::AUTHORIZATION, key.clone())
+        .json(&json!({ "title": "Renamed" }))
+        .await
+        .assert_status_ok();
+    state.content_store.flush_access().await.unwrap();
+    let hits: Vec<(String, i64)> = sqlx::query_as("SELECT alias, hits FROM collection_access ORDER BY alias")
+        .fetch_all(&state.db)
+        .await
+        .unwrap();
+    assert_eq!(hits, vec![("other".to_string(), 2)]);
+    assert_eq!(warmed(WarmupMode::Recent(1)).await, vec!["other"]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_single_flight_loading() {
+    let (dir, state, _server) = setup_collection("numbered", &numbered_collection(2000)).await;
+    let loads = || state.metrics.decompression_duration.get_sample_count();
+
+    let tasks: Vec<_> = (0..50)
+        .map(|_| {
+            let state = state.clone();
+            tokio::spawn(async move { state.content_store.get_collection("numbered").await.map(|e| e.collection.items.len()) })
+        })
+        .collect();
+    for task in tasks {
+        assert_eq!(task.await.unwrap().unwrap(), 2000);
+    }
+    assert_eq!(loads(), 1, "concurrent misses should share one disk read");
+    assert_eq!(state.metrics.cache_misses.get(), 1, "a shared load counts as one miss");
+
+    // A failing load is reported to every waiter and not cached.
+    let path = dir.path().join("collections/numbered.json.lz4");
+    let moved = dir.path().join("numbered.bak");
+    fs::rename(&path, &moved).unwrap();
+    state.content_store.evict("numbered").await;
+
+    let tasks: Vec<_> = (0..20)
+        .map(|_| {
+            let state = state.clone();
+            tokio::spawn(async move { state.content_store.get_collection("numbered").await.map(|_| ()) })
+        })
+        .collect();
+    for task in tasks {
+        let err = task.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("Storage file not found"), "unexpected error: {}", err);
+    }
+    let response = axum::response::IntoResponse::into_response(state.content_store.get_collection("numbered").await.unwrap_err());
+    assert_eq!(response.status(), http::StatusCode::NOT_FOUND);
+
+    fs::rename(&moved, &path).unwrap();
+    let entry = state.content_store.get_collection("numbered").await.unwrap();
+    assert_eq!(entry.collection.items.len(), 2000);
+    assert_eq!(loads(), 2);
+}
+
+#[tokio::test]
+async fn test_duplicate_item_ids() {
+    let mut content = numbered_collection(3);
+    content["items"][2]["id"] = json!("item-1");
+    let (_dir, state, server) = setup_collection("dupes", &content).await;
+
+    let response = server.get("/api/v1/content/collections/dupes").await;
+    response.assert_status(http::StatusCode::INTERNAL_SERVER_ERROR);
+    let error: serde_json::Value = response.json();
+    assert_eq!(error["code"], "INVALID_CONTENT");
+    assert!(error["message"].as_str().unwrap().contains("item-1"));
+
+    let auth = api_key(&state, &[Scope::Write]).await;
+    let response = server.post("/api/v1/content/collections")
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "alias": "poems", "name": "Poems", "content": content }))
+        .await;
+    response.assert_status_bad_request();
+    let error: serde_json::Value = response.json();
+    assert!(error["message"].as_str().unwrap().contains("Duplicate item id: item-1"));
+
+    let response = server.post("/api/v1/content/collections")
+        .add_header(http::header::AUTHORIZATION, auth)
+        .json(&json!({ "alias": "poems", "name": "Poems", "content": numbered_collection(500) }))
+        .await;
+    response.assert_status(http::StatusCode::CREATED);
+
+    let entry = state.content_store.get_collection("poems").await.unwrap();
+    assert_eq!(entry.item_position("item-420").unwrap(), 419);
+    assert!(entry.item_position("item-501").is_err());
+
+    let response = server.get("/api/v1/content/collections/poems/items?limit=1&cursor=item-499").await;
+    let page: serde_json::Value = response.json();
+    assert_eq!(page["items"][0]["id"], "item-500");
+}
+
+#[tokio::test]
+async fn test_chunked_storage_random_access() {
+    let (dir, state, server) = setup_collection("legacy", &numbered_collection(3)).await;
+    let auth = api_key(&state, &[Scope::Write]).await;
+    let loads = || state.metrics.decompression_duration.get_sample_count();
+
+    let mut content = numbered_collection(3000);
+    for item in content["items"].as_array_mut().unwrap() {
+        item["body"] = json!("Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(4));
+    }
+    let response = server.post("/api/v1/content/collections")
+        .add_header(http::header::AUTHORIZATION, auth)
+        .json(&json!({ "alias": "big", "name": "Big", "content": content }))
+        .await;
+    response.assert_status(http::StatusCode::CREATED);
+    let stored = fs::read(dir.path().join("collections/big.tlpc")).unwrap();
+    assert_eq!(&stored[..4], b"TLPC");
+
+    // Items and pages of an uncached chunked collection are read by seeking.
+    let response = server.get("/api/v1/content/collections/big/items/item-2500").await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["title"], "Item 2500");
+    let etag = response.header(http::header::ETAG);
+    let response = server.get("/api/v1/content/collections/big/items/item-2500")
+        .add_header(http::header::IF_NONE_MATCH, etag.clone())
+        .await;
+    response.assert_status(http::StatusCode::NOT_MODIFIED);
+
+    let page: serde_json::Value = server.get("/api/v1/content/collections/big/items?offset=250&limit=100").await.json();
+    let ids: Vec<_> = page["items"].as_array().unwrap().iter().map(|item| item["id"].as_str().unwrap()).collect();
+    assert_eq!(ids.len(), 100);
+    assert_eq!(ids[0], "item-251");
+    assert_eq!(ids[99], "item-350");
+    assert_eq!(page["total"], 3000);
+    let page: serde_json::Value = server.get("/api/v1/content/collections/big/items?cursor=item-2999").await.json();
+    assert_eq!(page["items"][0]["id"], "item-3000");
+    assert!(page["next_cursor"].is_null());
+    server.get("/api/v1/content/collections/big/items/missing").await.assert_status_not_found();
+    assert_eq!(loads(), 0, "no collection should have been decompressed as a whole");
+    assert_eq!(state.content_store.cached_entries().await, 0);
+
+    // The whole collection still loads, with the same item ETags once cached.
+    let response = server.get("/api/v1/content/collections/big").await;
+    assert_eq!(response.json::<ContentCollection>().items.len(), 3000);
+    assert_eq!(loads(), 1);
+    let response = server.get("/api/v1/content/collections/big/items/item-2500").await;
+    assert_eq!(response.header(http::header::ETAG), etag);
+
+    // Chunked files are not an LZ4 frame, so `lz4` is encoded on demand.
+    let response = server.get("/api/v1/content/collections/big")
+        .add_header(http::header::ACCEPT_ENCODING, http::HeaderValue::from_static("lz4"))
+        .await;
+    assert_eq!(response.header(http::header::CONTENT_ENCODING), "lz4");
+    let mut json = Vec::new();
+    lz4_flex::frame::FrameDecoder::new(response.as_bytes().as_ref()).read_to_end(&mut json).unwrap();
+    assert_eq!(serde_json::from_slice::<ContentCollection>(&json).unwrap().items.len(), 3000);
+
+    // Legacy single-frame files keep working for items and whole collections.
+    let response = server.get("/api/v1/content/collections/legacy/items/item-2").await;
+    assert_eq!(response.json::<serde_json::Value>()["title"], "Item 2");
+    let response = server.get("/api/v1/content/collections/legacy")
+        .add_header(http::header::ACCEPT_ENCODING, http::HeaderValue::from_static("lz4"))
+        .await;
+    assert_eq!(response.as_bytes().len() as u64, fs::metadata(dir.path().join("collections/legacy.json.lz4")).unwrap().len());
+}
+
+#[tokio::test]
+async fn test_streaming_collection_responses() {
+    let (_dir, state, server) = setup_collection("numbered", &numbered_collection(5000)).await;
+
+    let response = server.get("/api/v1/content/collections/numbered").await;
+    response.assert_status_ok();
+    assert_eq!(response.header(http::header::CONTENT_TYPE), "application/json");
+    assert!(response.maybe_header(http::header::CONTENT_LENGTH).is_none(), "large bodies are streamed");
+    let entry = state.content_store.get_collection("numbered").await.unwrap();
+    assert_eq!(response.as_bytes().as_ref(), serde_json::to_vec(&*entry.collection).unwrap().as_slice());
+    assert_eq!(response.header(http::header::ETAG), entry.etag());
+
+    let ndjson = http::HeaderValue::from_static("application/json;q=0.5, application/x-ndjson");
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(http::header::ACCEPT, ndjson.clone())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header(http::header::CONTENT_TYPE), "application/x-ndjson");
+    let etag = response.header(http::header::ETAG);
+    assert_ne!(etag, entry.etag());
+    let text = response.text();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines.len(), 5000);
+    assert_eq!(serde_json::from_str::<serde_json::Value>(lines[4999]).unwrap()["id"], "item-5000");
+
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(http::header::ACCEPT, ndjson)
+        .add_header(http::header::IF_NONE_MATCH, etag)
+        .await;
+    response.assert_status(http::StatusCode::NOT_MODIFIED);
+
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(http::header::ACCEPT, http::HeaderValue::from_static("*/*, application/x-ndjson;q=0.1"))
+        .await;
+    assert_eq!(response.header(http::header::CONTENT_TYPE), "application/json");
+
+    let response = server.get("/api/v1/content/collections/numbered")
+        .add_header(http::header::ACCEPT, http::HeaderValue::from_static("application/x-ndjson"))
+        .add_header(http::header::ACCEPT_ENCODING, http::HeaderValue::from_static("gzip"))
+        .await;
+    assert_eq!(response.header(http::header::CONTENT_ENCODING), "gzip");
+    assert_eq!(response.header(http::header::ETAG), format!("\"{}-ndjson-gzip\"", entry.content_hash));
+    let mut text = String::new();
+    flate2::read::GzDecoder::new(response.as_bytes().as_ref()).read_to_string(&mut text).unwrap();
+    assert_eq!(text.lines().count(), 5000);
+
+    // Collection fields larger than a chunk are written once.
+    let long_name = "n".repeat(100 * 1024);
+    let content = json!({ "id": "long", "name": long_name, "items": [{ "id": "1", "title": "One", "body": "" }] });
+    let (_dir, _state, server) = setup_collection("long", &content).await;
+    let response = server.get("/api/v1/content/collections/long").await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>(), content);
+}
+
+#[tokio::test]
+async fn test_stream_encoded_collection_responses() {
+    let (dir, state, _server) = setup_collection("numbered", &numbered_collection(5000)).await;
+    let state = Arc::new(AppState::new(state.db.clone(), Config {
+        stream_encoding_min_size: 1024,
+        ..test_config(dir.path().to_path_buf())
+    }).expect("Invalid configuration"));
+    let server = TestServer::new(create_router(state.clone())).unwrap();
+    let entry = state.content_store.get_collection("numbered").await.unwrap();
+    let json = serde_json::to_vec(&*entry.collection).unwrap();
+
+    let decode = |coding: &str, data: &[u8]| {
+        let mut out = Vec::new();
+        match coding {
+            "br" => brotli::Decompressor::new(data, 4096).read_to_end(&mut out).unwrap(),
+            "zstd" => zstd::stream::read::Decoder::new(data).unwrap().read_to_end(&mut out).unwrap(),
+            "gzip" => flate2::read::GzDecoder::new(data).read_to_end(&mut out).unwrap(),
+            _ => flate2::read::ZlibDecoder::new(data).read_to_end(&mut out).unwrap(),
+        };
+        out
+    };
+    for coding in ["br", "zstd", "gzip", "deflate"] {
+        let accept = http::HeaderValue::from_static(coding);
+        let response = server.get("/api/v1/content/collections/numbered")
+            .add_header(http::header::ACCEPT_ENCODING, accept.clone())
+            .await;
+        response.assert_status_ok();
+        assert_eq!(response.header(http::header::CONTENT_ENCODING), coding);
+        assert!(response.maybe_header(http::header::CONTENT_LENGTH).is_none(), "{} is streamed", coding);
+        assert_eq!(decode(coding, response.as_bytes()), json, "{}", coding);
+        let etag = response.header(http::header::ETAG);
+        assert_eq!(etag, format!("\"{}-{}\"", entry.content_hash, coding));
+
+        server.get("/api/v1/content/collections/numbered")
+            .add_header(http::header::ACCEPT_ENCODING, accept)
+            .add_header(http::header::IF_NONE_MATCH, etag)
+            .await
+            .assert_status(http::StatusCode::NOT_MODIFIED);
+    }
+}
+
+#[tokio::test]
+async fn test_translations() {
+    let (_dir, state, server) = setup_collection("numbered", &numbered_collection(1)).await;
+    let auth = api_key(&state, &[Scope::Admin]).await;
+    let poems = |titles: [&str; 2], ids: [&str; 2]| {
+        json!({ "id": "poems", "name": "Poems", "items": [
+            { "id": ids[0], "title": titles[0], "body": "" },
+            { "id": ids[1], "title": titles[1], "body": "" },
+        ] })
+    };
+    for (alias, language, private, aligned, content) in [
+        ("poems-en", "en", false, false, poems(["Morning", "Night"], ["1", "2"])),
+        ("poems-tr", "tr", false, true, poems(["Sabah", "Gece"], ["1", "2"])),
+        ("poems-pt", "pt-BR", false, true, poems(["Manhã", "Noite"], ["a", "b"])),
+        ("poems-de", "de", true, false, poems(["Morgen", "Nacht"], ["1", "2"])),
+    ] {
+        server.post("/api/v1/content/collections")
+            .add_header(http::header::AUTHORIZATION, auth.clone())
+            .json(&json!({
+                "alias": alias, "name": "Poems", "language": language, "private": private,
+                "translation_group": "poems", "aligned_by_position": aligned, "content": content
+            }))
+            .await
+            .assert_status(http::StatusCode::CREATED);
+    }
+
+    let response = server.get("/api/v1/content/collections/poems-en/translations")
+        .add_header(http::header::ACCEPT_LANGUAGE, http::HeaderValue::from_static("tr, en;q=0.8"))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.header(http::header::VARY), "accept-language");
+    let members: Vec<serde_json::Value> = response.json();
+    let aliases: Vec<_> = members.iter().map(|m| m["alias"].as_str().unwrap()).collect();
+    assert_eq!(aliases, vec!["poems-tr", "poems-en", "poems-pt"], "private translations are hidden");
+    assert_eq!(members[0]["translation_group"], "poems");
+
+    let response = server.get("/api/v1/content/collections/poems-en/items/2?lang=tr").await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["title"], "Gece");
+    assert_eq!(response.header(http::header::CONTENT_LANGUAGE), "tr");
+
+    // `pt` matches `pt-BR`; items without a shared id align by position
+    // only between collections that both opt in.
+    let response = server.get("/api/v1/content/collections/poems-tr/items/2?lang=pt").await;
+    assert_eq!(response.json::<serde_json::Value>()["title"], "Noite");
+    assert_eq!(response.header(http::header::CONTENT_LANGUAGE), "pt-BR");
+    server.get("/api/v1/content/collections/poems-en/items/2?lang=pt").await.assert_status_not_found();
+
+    let response = server.get("/api/v1/content/collections/poems-en?lang=fr,tr;q=0.5").await;
+    assert_eq!(response.json::<ContentCollection>().items[0].title, "Sabah");
+    let response = server.get("/api/v1/content/collections/poems-en/items?lang=tr&limit=1").await;
+    assert_eq!(response.json::<serde_json::Value>()["items"][0]["title"], "Sabah");
+    assert_eq!(response.header(http::header::CONTENT_LANGUAGE), "tr");
+
+    // Without `lang`, Accept-Language picks the translation, and falls back
+    // to the collection asked for when nothing matches.
+    let response = server.get("/api/v1/content/collections/poems-en")
+        .add_header(http::header::ACCEPT_LANGUAGE, http::HeaderValue::from_static("tr-TR, en;q=0.5"))
+        .await;
+    assert_eq!(response.json::<ContentCollection>().items[0].title, "Sabah");
+    assert_eq!(response.header(http::header::VARY), "accept, accept-encoding, accept-language");
+    let response = server.get("/api/v1/content/collections/poems-en/items/2")
+        .add_header(http::header::ACCEPT_LANGUAGE, http::HeaderValue::from_static("fr"))
+        .await;
+    response.assert_status_ok();
+    assert_eq!(response.json::<serde_json::Value>()["title"], "Night");
+    assert_eq!(response.header(http::header::CONTENT_LANGUAGE), "en");
+    assert!(response.headers().get_all(http::header::VARY).iter().any(|v| v == "accept-language"));
+    let response = server.get("/api/v1/content/collections/poems-tr/toc")
+        .add_header(http::header::ACCEPT_LANGUAGE, http::HeaderValue::from_static("en"))
+        .await;
+    assert_eq!(response.json::<serde_json::Value>()["items"][0]["title"], "Morning");
+
+    server.get("/api/v1/content/collections/poems-en/items/1?lang=de").await.assert_status_not_found();
+    let response = server.get("/api/v1/content/collections/poems-en/items/1?lang=de")
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .await;
+    assert_eq!(response.json::<serde_json::Value>()["title"], "Morgen");
+
+    let response = server.get("/api/v1/content/collections/numbered/translations").await;
+    let members: Vec<serde_json::Value> = response.json();
+    assert_eq!(members.len(), 1);
+    assert!(members[0]["translation_group"].is_null());
+
+    server.post("/api/v1/content/collections")
+        .add_header(http::header::AUTHORIZATION, auth)
+        .json(&json!({ "alias": "x", "name": "x", "translation_group": "Not Valid", "content": numbered_collection(1) }))
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn test_rich_item_fields() {
+    let (_dir, state, server) = setup_collection("numbered", &numbered_collection(2)).await;
+    let auth = api_key(&state, &[Scope::Write]).await;
+
+    // Items stored without the structured fields read back unchanged.
+    let item: serde_json::Value = server.get("/api/v1/content/collections/numbered/items/item-1").await.json();
+    assert_eq!(item, json!({ "id": "item-1", "title": "Item 1", "body": "Body 1" }));
+
+    let rich = json!({
+        "id": "1",
+        "title": "Quatrain I",
+        "body": "Awake!",
+        "tags": ["dawn", "wine"],
+        "authors": ["Omar Khayyam", "Edward FitzGerald"],
+        "metadata": { "edition": 1, "source": "1859" },
+        "position": 10,
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-02-01T00:00:00Z"
+    });
+    let upsert = |items: serde_json::Value| json!({
+        "alias": "rubai", "name": "Rubai", "content": { "id": "rubai", "name": "Rubai", "items": items }
+    });
+    server.post("/api/v1/content/collections")
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&upsert(json!([rich])))
+        .await
+        .assert_status(http::StatusCode::CREATED);
+    let item: serde_json::Value = server.get("/api/v1/content/collections/rubai/items/1").await.json();
+    assert_eq!(item, rich);
+    let full = server.get("/api/v1/content/collections/rubai/items/1").await.text();
+    let response = server.get("/api/v1/content/collections/rubai/items/1?exclude=body").await;
+    assert_eq!(response.text(), full.replace(",\"body\":\"Awake!\"", ""), "projections keep the field order");
+
+    for (field, value, message) in [
+        ("tags", json!(["dawn", "dawn"]), "duplicate value in tags: dawn"),
+        ("authors", json!([" "]), "authors must not contain empty values"),
+        ("updated_at", json!("2023-01-01T00:00:00Z"), "updated_at is before created_at"),
+    ] {
+        let mut invalid = rich.clone();
+        invalid[field] = value;
+        let response = server.put("/api/v1/content/collections/rubai")
+            .add_header(http::header::AUTHORIZATION, auth.clone())
+            .json(&upsert(json!([invalid])))
+            .await;
+        response.assert_status_bad_request();
+        let error: serde_json::Value = response.json();
+        assert!(error["message"].as_str().unwrap().contains(message), "{}", error["message"]);
+    }
+    let response = server.post("/api/v1/content/collections")
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "alias": "bad", "name": "Bad", "content": { "id": "bad", "name": "Bad", "items": [{ "id": "1", "title": "x", "body": "x", "position": "first" }] } }))
+        .await;
+    assert!(response.status_code().is_client_error());
+
+    // Item writes stamp their timestamps.
+    let response = server.post("/api/v1/content/collections/rubai/items")
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "id": "2", "title": "Quatrain II", "body": "Dreaming", "tags": ["dawn"] }))
+        .await;
+    response.assert_status(http::StatusCode::CREATED);
+    let created: serde_json::Value = response.json();
+    assert!(created["created_at"].is_string());
+    assert!(created["updated_at"].is_null());
+    // The stamped creation time is validated like a given one.
+    let response = server.post("/api/v1/content/collections/rubai/items")
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "id": "3", "title": "x", "body": "x", "updated_at": "2000-01-01T00:00:00Z" }))
+        .await;
+    response.assert_status_bad_request();
+    assert!(response.text().contains("updated_at is before created_at"));
+
+    let response = server.patch("/api/v1/content/collections/rubai/items/2")
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "tags": ["night"], "position": 20, "metadata": { "mood": "wistful" } }))
+        .await;
+    response.assert_status_ok();
+    let updated: serde_json::Value = response.json();
+    assert_eq!(updated["tags"], json!(["night"]));
+    assert_eq!(updated["position"], 20);
+    assert_eq!(updated["metadata"]["mood"], "wistful");
+    let timestamp = |value: &serde_json::Value| chrono::DateTime::parse_from_rfc3339(value.as_str().unwrap()).unwrap();
+    assert!(timestamp(&updated["updated_at"]) >= timestamp(&created["created_at"]));
+
+    let response = server.patch("/api/v1/content/collections/rubai/items/2")
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "title": "Quatrain II" }))
+        .await;
+    assert_eq!(response.json::<serde_json::Value>()["position"], 20, "absent fields are kept");
+    let response = server.patch("/api/v1/content/collections/rubai/items/2")
+        .add_header(http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "position": null }))
+        .await;
+    response.assert_status_ok();
+    assert!(response.json::<serde_json::Value>().get("position").is_none(), "null clears the position");
+
+    server.patch("/api/v1/content/collections/rubai/items/2")
+        .add_header(http::header::AUTHORIZATION, auth)
+        .json(&json!({ "tags": [""] }))
+        .await
+        .assert_status_bad_request();
+    let item: serde_json::Value = server.get("/api/v1/content/collections/rubai/items/2").await.json();
+    assert_eq!(item["tags"], json!(["night"]), "rejected patches leave the item unchanged");
+}
+
+#[tokio::test]
+async fn test_item_filtering_and_sorting() {
+    let content = json!({ "id": "poems", "name": "Poems", "items": [
+        { "id": "a", "title": "Awake", "body": "", "tags": ["Love", "dawn"], "authors": ["Omar Khayyam"], "position": 3, "metadata": { "edition": 1 } },
+        { "id": "b", "title": "Come", "body": "", "tags": ["wine"], "authors": ["Omar Khayyam", "Edward FitzGerald"], "position": 1, "metadata": { "edition": 2 } },
+        { "id": "c", "title": "Beloved", "body": "", "tags": ["love"], "authors": ["Hafez"], "metadata": { "edition": [1, 2] } },
+        { "id": "d", "title": "Dreaming", "body": "", "tags": ["love", "wine"], "authors": ["Omar Khayyam"], "position": 2 },
+    ] });
+    let (_dir, _state, server) = setup_collection("poems", &content).await;
+    let ids = |response: axum_test::TestResponse| -> Vec<String> {
+        response.assert_status_ok();
+        let page: serde_json::Value = response.json();
+        page["items"].as_array().unwrap().iter().map(|item| item["id"].as_str().unwrap().to_string()).collect()
+    };
+
+    assert_eq!(ids(server.get("/api/v1/content/collections/poems/items?tag=love").await), ["a", "c", "d"]);
+    assert_eq!(ids(server.get("/api/v1/content/collections/poems/items?tag=love&tag=wine").await), ["d"]);
+    assert_eq!(ids(server.get("/api/v1/content/collections/poems/items?tag=love&author=khayyam").await), ["a", "d"]);
+    assert_eq!(ids(server.get("/api/v1/content/collections/poems/items?metadata.edition=1").await), ["a", "c"]);
+    assert_eq!(ids(server.get("/api/v1/content/collections/poems/items?sort=-position").await), ["a", "d", "b", "c"]);
+    assert_eq!(ids(server.get("/api/v1/content/collections/poems/items?sort=position").await), ["b", "d", "a", "c"]);
+    assert_eq!(ids(server.get("/api/v1/content/collections/poems/items?sort=title").await), ["a", "c", "b", "d"]);
+
+    let response = server.get("/api/v1/content/collections/poems/items?author=khayyam&sort=-position&limit=2").await;
+    let page: serde_json::Value = response.json();
+    assert_eq!(page["total"], 3);
+    assert_eq!(page["next_cursor"], "d");
+    let response = server.get("/api/v1/content/collections/poems/items?author=khayyam&sort=-position&limit=2&cursor=d").await;
+    assert_eq!(ids(response), ["b"]);
+
+    let page: serde_json::Value = server.get("/api/v1/content/collections/poems/items?tag=wine&fields=id,title").await.json();
+    assert_eq!(page["items"], json!([{ "id": "b", "title": "Come" }, { "id": "d", "title": "Dreaming" }]));
+
+    let page: serde_json::Value = server.get("/api/v1/content/collections/poems/items?tag=wine&_=123").await.json();
+    assert_eq!(page["total"], 2, "the cache buster is allowed");
+
+    for query in ["sort=colour", "sort=-", "fields=id,colour", "tag=", "metadata.=1", "tags=love", "genre=poetry", "limit=ten", "limit=0", "cursor=zz"] {
+        let response = server.get(&format!("/api/v1/content/collections/poems/items?{}", query)).await;
+        response.assert_status_bad_request();
+        let error: serde_json::Value = response.json();
+        assert_eq!(error["code"], "INVALID_QUERY", "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn test_field_selection_and_toc() {
+    let content = json!({ "id": "poems", "name": "Poems", "items": [
+        { "id": "a", "title": "Awake", "body": "Awake! for Morning", "tags": ["dawn"], "position": 2 },
+        { "id": "b", "title": "Ölüm", "body": "Çiçek", "position": 1 },
+    ] });
+    let (_dir, _state, server) = setup_collection("poems", &content).await;
+
+    let response = server.get("/api/v1/content/collections/poems?exclude=body").await;
+    response.assert_status_ok();
+    let etag = response.header(http::header::ETAG);
+    assert_eq!(response.json::<serde_json::Value>(), json!({ "id": "poems", "name": "Poems", "items": [
+        { "id": "a", "title": "Awake", "tags": ["dawn"], "position": 2 },
+        { "id": "b", "title": "Ölüm", "position": 1 },
+    ] }));
+    let full = server.get("/api/v1/content/collections/poems").await;
+    assert_ne!(full.header(http::header::ETAG), etag);
+    server.get("/api/v1/content/collections/poems?exclude=body")
+        .add_header(http::header::IF_NONE_MATCH, etag)
+        .await
+        .assert_status(http::StatusCode::NOT_MODIFIED);
+
+    let response = server.get("/api/v1/content/collections/poems?fields=id,title,position&exclude=position")
+        .add_header(http::header::ACCEPT, http::HeaderValue::from_static("application/x-ndjson"))
+        .await;
+    assert_eq!(response.text(), "{\"id\":\"a\",\"title\":\"Awake\"}\n{\"id\":\"b\",\"title\":\"Ölüm\"}\n");
+
+    let response = server.get("/api/v1/content/collections/poems/items/a?fields=title,tags").await;
+    response.assert_status_ok();
+    let item_etag = response.header(http::header::ETAG);
+    assert_eq!(response.json::<serde_json::Value>(), json!({ "title": "Awake", "tags": ["dawn"] }));
+    let full = server.get("/api/v1/content/collections/poems/items/a").await;
+    assert_ne!(full.header(http::header::ETAG), item_etag);
+
+    let response = server.get("/api/v1/content/collections/poems/toc").await;
+    response.assert_status_ok();
+    let etag = response.header(http::header::ETAG);
+    assert!(etag.to_str().unwrap().starts_with("W/\""));
+    assert_eq!(response.json::<serde_json::Value>(), json!({ "id": "poems", "name": "Poems", "items": [
+        { "id": "a", "title": "Awake", "body_length": 18 },
+        { "id": "b", "title": "Ölüm", "body_length": 5 },
+    ] }));
+    server.get("/api/v1/content/collections/poems/toc")
+        .add_header(http::header::IF_NONE_MATCH, etag)
+        .await
+        .assert_status(http::StatusCode::NOT_MODIFIED);
+    server.get("/api/v1/content/collections/missing/toc").await.assert_status_not_found();
+
+    for path in ["poems?fields=colour", "poems?exclude=", "poems/items/a?exclude=body,colour"] {
+        let response = server.get(&format!("/api/v1/content/collections/{}", path)).await;
+        response.assert_status_bad_request();
+        let error: serde_json::Value = response.json();
+        assert_eq!(error["code"], "INVALID_QUERY", "{}", path);
+    }
+}